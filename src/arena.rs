@@ -0,0 +1,307 @@
+use std::collections::BTreeMap;
+
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    editor::editor_closed,
+    map::{Geometry, LightKind, Map, MapLight, MapMaterial, PlayerSpawn, Shape, TargetVolume},
+    player::game_paused,
+    world::CurrentMap,
+};
+
+pub struct ArenaPlugin;
+
+impl Plugin for ArenaPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ArenaParams>().add_systems(
+            Update,
+            arena_panel.run_if(game_paused).run_if(editor_closed),
+        );
+    }
+}
+
+// same params (seed included) always give the same arena
+#[derive(Resource, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ArenaParams {
+    pub seed: u64,
+    // side length of the square floor
+    pub size: f32,
+    // 0..1, chance of a grid cell getting something in it
+    pub density: f32,
+    // 0..1, how tall walls get and how many raised platforms there are
+    pub verticality: f32,
+}
+
+impl Default for ArenaParams {
+    fn default() -> Self {
+        Self {
+            seed: 1,
+            size: 80.0,
+            density: 0.35,
+            verticality: 0.4,
+        }
+    }
+}
+
+// features sit on a grid so they don't overlap each other
+const CELL: f32 = 8.0;
+// nothing gets placed this close to the middle, that's where the player spawns
+const CLEAR_RADIUS: f32 = 6.0;
+
+pub fn generate(params: &ArenaParams) -> Map {
+    let mut rng = fastrand::Rng::with_seed(params.seed);
+    let size = params.size.max(CELL * 3.0);
+    let half = size / 2.0;
+    let density = params.density.clamp(0.0, 1.0);
+    let verticality = params.verticality.clamp(0.0, 1.0);
+    let wall_height = 4.0 + verticality * 8.0;
+
+    let mut map = Map {
+        name: format!("arena-{}", params.seed),
+        materials: materials(),
+        ..default()
+    };
+
+    map.geometry.push(Geometry {
+        shape: Shape::Plane { size },
+        position: [0.0; 3],
+        rotation: [0.0; 3],
+        material: "floor".into(),
+    });
+
+    // outer walls
+    for (position, wall_size) in [
+        ([half, wall_height / 2.0, 0.0], [1.0, wall_height, size]),
+        ([-half, wall_height / 2.0, 0.0], [1.0, wall_height, size]),
+        ([0.0, wall_height / 2.0, half], [size, wall_height, 1.0]),
+        ([0.0, wall_height / 2.0, -half], [size, wall_height, 1.0]),
+    ] {
+        map.geometry.push(Geometry {
+            shape: Shape::Cuboid { size: wall_size },
+            position,
+            rotation: [0.0; 3],
+            material: "wall".into(),
+        });
+    }
+
+    let cells = (size / CELL).floor() as i32;
+    let origin = -(cells as f32) * CELL / 2.0 + CELL / 2.0;
+
+    for ix in 0..cells {
+        for iz in 0..cells {
+            let x = origin + ix as f32 * CELL;
+            let z = origin + iz as f32 * CELL;
+
+            // the rolls happen for every cell so skipping one doesn't shift the rest
+            let roll = rng.f32();
+            let kind = rng.f32();
+            let jitter = [rng.f32() - 0.5, rng.f32() - 0.5];
+            let variation = [rng.f32(), rng.f32(), rng.f32()];
+            let side = rng.u8(0..4);
+
+            if roll > density || Vec2::new(x, z).length() < CLEAR_RADIUS {
+                continue;
+            }
+
+            let x = snap(x + jitter[0] * CELL * 0.3);
+            let z = snap(z + jitter[1] * CELL * 0.3);
+
+            if kind < verticality * 0.5 {
+                platform(&mut map, x, z, side, variation, verticality);
+            } else if kind < 0.55 {
+                cover_block(&mut map, x, z, side, variation);
+            } else if kind < 0.8 {
+                cover_wall(&mut map, x, z, side, variation, wall_height);
+            } else {
+                pillar(&mut map, x, z, variation, wall_height);
+            }
+        }
+    }
+
+    map.lights.push(MapLight {
+        kind: LightKind::Directional,
+        position: [0.0, 20.0, 0.0],
+        rotation: [-60.0, 30.0, 0.0],
+        color: [1.0, 0.97, 0.9],
+        intensity: 12000.0,
+        range: 0.0,
+        shadows: true,
+    });
+    for (x, z) in [(0.5, 0.5), (-0.5, 0.5), (0.5, -0.5), (-0.5, -0.5)] {
+        map.lights.push(MapLight {
+            kind: LightKind::Point,
+            position: [x * half, wall_height + 2.0, z * half],
+            rotation: [0.0; 3],
+            color: [1.0, 1.0, 1.0],
+            intensity: 8000.0,
+            range: size,
+            shadows: false,
+        });
+    }
+
+    map.player_spawns.push(PlayerSpawn {
+        position: [0.0, 0.5, 0.0],
+        yaw: 180.0,
+    });
+
+    // targets float along the -X wall, facing the spawn
+    map.target_volumes.push(TargetVolume {
+        min: [-half + 2.0, 1.0, -half / 2.0],
+        max: [-half + 2.0, wall_height - 1.0, half / 2.0],
+        count: 3,
+        health: 1,
+    });
+
+    map
+}
+
+fn cover_block(map: &mut Map, x: f32, z: f32, side: u8, variation: [f32; 3]) {
+    let width = snap(1.0 + variation[0] * 2.0);
+    let height = snap(1.0 + variation[1] * 1.5);
+    map.geometry.push(Geometry {
+        shape: Shape::Cuboid {
+            size: [width, height, width],
+        },
+        position: [x, height / 2.0, z],
+        rotation: [0.0, side as f32 * 90.0, 0.0],
+        material: "cover".into(),
+    });
+}
+
+fn cover_wall(map: &mut Map, x: f32, z: f32, side: u8, variation: [f32; 3], wall_height: f32) {
+    let length = snap(3.0 + variation[0] * 3.0);
+    let height = snap(2.0 + variation[1] * (wall_height - 2.0) * 0.5);
+    map.geometry.push(Geometry {
+        shape: Shape::Cuboid {
+            size: [length, height, 0.6],
+        },
+        position: [x, height / 2.0, z],
+        rotation: [0.0, side as f32 * 90.0, 0.0],
+        material: "wall".into(),
+    });
+}
+
+fn pillar(map: &mut Map, x: f32, z: f32, variation: [f32; 3], wall_height: f32) {
+    let radius = 0.5 + variation[0] * 0.75;
+    let height = snap(wall_height * (0.6 + variation[1] * 0.4));
+    map.geometry.push(Geometry {
+        shape: Shape::Cylinder { radius, height },
+        position: [x, height / 2.0, z],
+        rotation: [0.0; 3],
+        material: "wall".into(),
+    });
+}
+
+// a raised slab with a ramp on one side leading up to it
+fn platform(map: &mut Map, x: f32, z: f32, side: u8, variation: [f32; 3], verticality: f32) {
+    let width = snap(3.0 + variation[0] * 2.0);
+    let height = snap(1.5 + variation[1] * 4.0 * verticality);
+    let thickness = 0.5;
+    map.geometry.push(Geometry {
+        shape: Shape::Cuboid {
+            size: [width, thickness, width],
+        },
+        position: [x, height - thickness / 2.0, z],
+        rotation: [0.0; 3],
+        material: "platform".into(),
+    });
+
+    // the ramp's tall side faces the platform
+    let yaw = side as f32 * 90.0;
+    let tall_side = Quat::from_rotation_y(yaw.to_radians()) * Vec3::NEG_Z;
+    let ramp_length = height * 2.0;
+    let center = Vec3::new(x, height / 2.0, z) - tall_side * (width + ramp_length) / 2.0;
+    map.geometry.push(Geometry {
+        shape: Shape::Ramp {
+            size: [width.min(3.0), height, ramp_length],
+        },
+        position: center.into(),
+        rotation: [0.0, yaw, 0.0],
+        material: "ramp".into(),
+    });
+}
+
+fn snap(value: f32) -> f32 {
+    (value * 2.0).round() / 2.0
+}
+
+fn materials() -> BTreeMap<String, MapMaterial> {
+    let material = |color: [f32; 4], roughness: f32| MapMaterial {
+        color,
+        roughness,
+        ..default()
+    };
+
+    BTreeMap::from([
+        ("floor".to_string(), material([0.29, 0.0, 0.51, 1.0], 0.8)),
+        ("wall".to_string(), material([0.55, 0.55, 0.6, 1.0], 0.7)),
+        ("cover".to_string(), material([0.8, 0.45, 0.1, 1.0], 0.6)),
+        ("platform".to_string(), material([0.2, 0.5, 0.7, 1.0], 0.5)),
+        ("ramp".to_string(), material([0.3, 0.65, 0.35, 1.0], 0.5)),
+    ])
+}
+
+fn arena_panel(
+    mut contexts: EguiContexts,
+    mut params: ResMut<ArenaParams>,
+    mut maps: ResMut<Assets<Map>>,
+    mut current: ResMut<CurrentMap>,
+) {
+    egui::Window::new("Arena generator").show(contexts.ctx_mut(), |ui| {
+        ui.horizontal(|ui| {
+            ui.label("Seed");
+            ui.add(egui::DragValue::new(&mut params.seed));
+            if ui.button("Random").clicked() {
+                params.seed = fastrand::u64(..);
+            }
+        });
+        ui.add(egui::Slider::new(&mut params.size, 30.0..=400.0).text("size"));
+        ui.add(egui::Slider::new(&mut params.density, 0.0..=1.0).text("density"));
+        ui.add(egui::Slider::new(&mut params.verticality, 0.0..=1.0).text("verticality"));
+        if ui.button("Generate").clicked() {
+            // swapping the handle makes world.rs build it
+            current.0 = maps.add(generate(&params));
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn inside(point: [f32; 3], half: f32) -> bool {
+        point[0].abs() < half && point[2].abs() < half && point[1] >= 0.0
+    }
+
+    #[test]
+    fn same_seed_same_arena() {
+        let params = ArenaParams::default();
+        assert_eq!(generate(&params), generate(&params));
+
+        let other = ArenaParams {
+            seed: params.seed + 1,
+            ..params.clone()
+        };
+        assert_ne!(generate(&params).geometry, generate(&other).geometry);
+    }
+
+    #[test]
+    fn spawns_and_targets_inside() {
+        for seed in 0..20 {
+            let params = ArenaParams { seed, ..default() };
+            let half = params.size / 2.0;
+            let map = generate(&params);
+
+            assert!(!map.player_spawns.is_empty());
+            for spawn in &map.player_spawns {
+                assert!(inside(spawn.position, half), "seed {seed} spawn");
+            }
+            for volume in &map.target_volumes {
+                assert!(inside(volume.min, half), "seed {seed} targets");
+                assert!(inside(volume.max, half), "seed {seed} targets");
+            }
+        }
+    }
+}
@@ -0,0 +1,11 @@
+use bevy_fps_test::{cli::LaunchOptions, server::dedicated_server};
+
+// Dedicated deathmatch server, same as running the game with --server.
+//
+//     cargo run --bin server -- --port 27015 --map maps/arena.map.ron --max-rewind 0.25
+//
+// --latency, --jitter, --loss, --duplicate and --reorder make the network worse on purpose,
+// see netsim.rs. The rest of the options are in cli.rs.
+fn main() {
+    dedicated_server(&LaunchOptions::from_args()).run();
+}
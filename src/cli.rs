@@ -0,0 +1,306 @@
+use std::path::PathBuf;
+
+use bevy::{app::AppExit, prelude::*, window::WindowMode};
+use thiserror::Error;
+
+use crate::{
+    arena::{generate, ArenaParams},
+    demo::{playing_demo, DemoCommand},
+    map::Map,
+    netsim::NetConditions,
+    player::{Player, PlayerCamera, Sensitivity},
+    server::ServerConfig,
+    world::CurrentMap,
+};
+
+// What the game starts into, so drills can be launched from scripts and desktop shortcuts:
+//
+//     bevy-fps-test --map maps/arena.map.ron --seed 7 --sensitivity 0.02 --fov 80
+//     bevy-fps-test --scenario arena --seed 7 --fullscreen
+//     bevy-fps-test --headless --demo demos/1700000000.demo
+//     bevy-fps-test --server --port 27015
+//
+// Both binaries take the same arguments (the server ignores the ones about the player and
+// the window). Values can also be written --seed=7. Anything not given stays as it was.
+pub const USAGE: &str = "\
+usage: bevy-fps-test [options]
+
+  --map <path>              map to start on, an asset path like maps/arena.map.ron
+  --scenario <name>         start on a built-in scenario instead, `arena` is a generated
+                            arena laid out from --seed
+  --seed <n>                seed for targets and spawn points
+  --sensitivity <n>         mouse sensitivity, degrees per count (0.015)
+  --fov <degrees>           vertical field of view
+  --windowed, --fullscreen, --borderless
+  --resolution <w>x<h>      window size, like 1920x1080
+  --headless                no window, sound or rendering
+  --demo <path>             play a demo, with --headless the game quits once it's over
+  --connect <host[:port]>   join a server
+  --server                  run a dedicated server instead of the game
+  --port <n>                port the server listens on (27015)
+  --max-rewind <seconds>    how far the server rewinds for hitscan shots (0.25)
+  --latency <ms>, --jitter <ms>, --loss <%>, --duplicate <%>, --reorder <%>
+                            make the network worse on purpose, see netsim.rs
+  --help                    show this
+";
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum Mode {
+    #[default]
+    Game,
+    // the game without a window, for playing demos back from scripts
+    Headless,
+    // a dedicated server, see server.rs
+    Server,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Scenario {
+    Arena,
+}
+
+#[derive(Resource, Clone, Debug, Default)]
+pub struct LaunchOptions {
+    pub mode: Mode,
+    // asset path
+    pub map: Option<String>,
+    pub scenario: Option<Scenario>,
+    pub seed: Option<u64>,
+    pub sensitivity: Option<f32>,
+    // vertical, degrees
+    pub fov: Option<f32>,
+    pub window_mode: WindowMode,
+    pub resolution: Option<Vec2>,
+    pub demo: Option<PathBuf>,
+    pub connect: Option<String>,
+    pub port: Option<u16>,
+    pub max_rewind: Option<f32>,
+    pub net: NetConditions,
+}
+
+#[derive(Debug, Error, PartialEq)]
+pub enum CliError {
+    #[error("unknown option '{0}'")]
+    Unknown(String),
+    #[error("{0} needs a value")]
+    Missing(String),
+    #[error("bad value '{value}' for {key}")]
+    BadValue { key: String, value: String },
+    #[error("{0} and {1} can't be used together")]
+    Conflict(&'static str, &'static str),
+}
+
+impl LaunchOptions {
+    // prints the usage and exits when the arguments are wrong or --help was asked for
+    pub fn from_args() -> Self {
+        let args: Vec<String> = std::env::args().skip(1).collect();
+        if args.iter().any(|arg| arg == "--help" || arg == "-h") {
+            println!("{USAGE}");
+            std::process::exit(0);
+        }
+        match Self::parse(args) {
+            Ok(options) => options,
+            Err(err) => {
+                eprintln!("{err}\n\n{USAGE}");
+                std::process::exit(2);
+            }
+        }
+    }
+
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Self, CliError> {
+        let mut options = Self::default();
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let (key, inline) = match arg.split_once('=') {
+                Some((key, value)) => (key.to_string(), Some(value.to_string())),
+                None => (arg, None),
+            };
+
+            // switches first, everything after that takes a value
+            let mode = match key.as_str() {
+                "--windowed" => Some(WindowMode::Windowed),
+                "--fullscreen" => Some(WindowMode::Fullscreen),
+                "--borderless" => Some(WindowMode::BorderlessFullscreen),
+                _ => None,
+            };
+            if let Some(mode) = mode {
+                options.window_mode = mode;
+                continue;
+            }
+            match key.as_str() {
+                "--headless" => {
+                    options.set_mode(Mode::Headless)?;
+                    continue;
+                }
+                "--server" => {
+                    options.set_mode(Mode::Server)?;
+                    continue;
+                }
+                _ => {}
+            }
+
+            let Some(value) = inline.or_else(|| args.next()) else {
+                return Err(CliError::Missing(key));
+            };
+            let bad_value = || CliError::BadValue {
+                key: key.clone(),
+                value: value.clone(),
+            };
+            let number = || value.parse::<f32>().map_err(|_| bad_value());
+
+            match key.as_str() {
+                "--map" => options.map = Some(value),
+                "--scenario" => {
+                    options.scenario = match value.as_str() {
+                        "arena" => Some(Scenario::Arena),
+                        _ => return Err(bad_value()),
+                    }
+                }
+                "--seed" => options.seed = Some(value.parse().map_err(|_| bad_value())?),
+                "--sensitivity" => options.sensitivity = Some(number()?),
+                "--fov" => {
+                    let fov = number()?;
+                    if !(1.0..180.0).contains(&fov) {
+                        return Err(bad_value());
+                    }
+                    options.fov = Some(fov);
+                }
+                "--resolution" => {
+                    let (width, height) = value
+                        .split_once('x')
+                        .and_then(|(w, h)| Some((w.parse().ok()?, h.parse().ok()?)))
+                        .filter(|(w, h): &(f32, f32)| *w > 0.0 && *h > 0.0)
+                        .ok_or_else(bad_value)?;
+                    options.resolution = Some(Vec2::new(width, height));
+                }
+                "--demo" => options.demo = Some(value.into()),
+                "--connect" => options.connect = Some(value),
+                "--port" => options.port = Some(value.parse().map_err(|_| bad_value())?),
+                "--max-rewind" => options.max_rewind = Some(number()?.max(0.0)),
+                "--latency" => options.net.latency = number()?.max(0.0),
+                "--jitter" => options.net.jitter = number()?.max(0.0),
+                "--loss" => options.net.loss = number()?.max(0.0),
+                "--duplicate" => options.net.duplicate = number()?.max(0.0),
+                "--reorder" => options.net.reorder = number()?.max(0.0),
+                _ => return Err(CliError::Unknown(key)),
+            }
+        }
+
+        if options.map.is_some() && options.scenario.is_some() {
+            return Err(CliError::Conflict("--map", "--scenario"));
+        }
+        // a demo brings its own map
+        if options.demo.is_some() && (options.map.is_some() || options.scenario.is_some()) {
+            return Err(CliError::Conflict("--demo", "--map/--scenario"));
+        }
+        if options.mode == Mode::Headless && options.connect.is_some() {
+            return Err(CliError::Conflict("--headless", "--connect"));
+        }
+        Ok(options)
+    }
+
+    fn set_mode(&mut self, mode: Mode) -> Result<(), CliError> {
+        if self.mode != Mode::Game && self.mode != mode {
+            return Err(CliError::Conflict("--headless", "--server"));
+        }
+        self.mode = mode;
+        Ok(())
+    }
+
+    pub fn server_config(&self) -> ServerConfig {
+        let default = ServerConfig::default();
+        ServerConfig {
+            port: self.port.unwrap_or(default.port),
+            map: self.map.clone().unwrap_or(default.map),
+            max_rewind: self.max_rewind.unwrap_or(default.max_rewind),
+        }
+    }
+
+    pub fn window(&self) -> Window {
+        let mut window = Window {
+            mode: self.window_mode,
+            ..default()
+        };
+        if let Some(size) = self.resolution {
+            window.resolution.set(size.x, size.y);
+        }
+        window
+    }
+}
+
+// Applies the options once the player and the default map are in. Needs LaunchOptions
+// inserted, the seed is picked up by RngPlugin and the rest by main.rs and client.rs.
+pub struct LaunchPlugin;
+
+impl Plugin for LaunchPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(PostStartup, (start_map, set_view, start_demo));
+    }
+}
+
+// Quits once the demo from --demo is over, or straight away if it wouldn't load. Playback
+// has already started by the end of the first frame.
+pub struct ExitAfterDemoPlugin;
+
+impl Plugin for ExitAfterDemoPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Last, exit_after_demo.run_if(not(playing_demo)));
+    }
+}
+
+// swapping the handle makes world.rs build the map instead of the default one
+fn start_map(
+    options: Res<LaunchOptions>,
+    asset_server: Res<AssetServer>,
+    mut maps: ResMut<Assets<Map>>,
+    mut current: ResMut<CurrentMap>,
+    mut params: Option<ResMut<ArenaParams>>,
+) {
+    if let Some(map) = &options.map {
+        current.0 = asset_server.load(map.clone());
+    }
+    match options.scenario {
+        Some(Scenario::Arena) => {
+            let mut arena = ArenaParams::default();
+            if let Some(seed) = options.seed {
+                arena.seed = seed;
+            }
+            current.0 = maps.add(generate(&arena));
+            // the Arena generator window starts from it
+            if let Some(params) = params.as_mut() {
+                **params = arena;
+            }
+        }
+        None => {}
+    }
+}
+
+fn set_view(
+    options: Res<LaunchOptions>,
+    mut player_q: Query<&mut Sensitivity, With<Player>>,
+    mut cam_q: Query<&mut Projection, With<PlayerCamera>>,
+) {
+    if let Some(sensitivity) = options.sensitivity {
+        for mut player_sensitivity in player_q.iter_mut() {
+            player_sensitivity.0 = sensitivity;
+        }
+    }
+    if let Some(fov) = options.fov {
+        for mut projection in cam_q.iter_mut() {
+            if let Projection::Perspective(perspective) = projection.as_mut() {
+                perspective.fov = fov.to_radians();
+            }
+        }
+    }
+}
+
+fn start_demo(options: Res<LaunchOptions>, mut commands: EventWriter<DemoCommand>) {
+    if let Some(path) = &options.demo {
+        commands.send(DemoCommand::Play(path.clone()));
+    }
+}
+
+fn exit_after_demo(mut exit: EventWriter<AppExit>) {
+    info!("demo over, quitting");
+    exit.send(AppExit);
+}
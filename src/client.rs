@@ -0,0 +1,578 @@
+use std::{
+    collections::VecDeque,
+    io::ErrorKind,
+    net::{SocketAddr, ToSocketAddrs, UdpSocket},
+};
+
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts};
+use bevy_rapier3d::prelude::Velocity;
+
+use crate::{
+    cli::LaunchOptions,
+    demo::run_demo,
+    editor::editor_closed,
+    input::InputFrame,
+    net::{
+        view_angles, view_rotation, ClientMessage, NetInput, ServerMessage, Snapshot, DEFAULT_PORT,
+        HELLO_INTERVAL, INPUT_REDUNDANCY, MAX_PACKET, TICK_RATE, TIMEOUT, VERSION,
+    },
+    netsim::{NetConditions, NetSocket},
+    player::{game_paused, Grounded, Movement, Paused, Player, PlayerCamera, TargetHit},
+    prediction::{smooth_corrections, Prediction},
+    world::CurrentMap,
+};
+
+// Playing on a dedicated server (see server.rs). The local player keeps running its own
+// systems, its inputs go to the server too and prediction.rs pulls it back whenever the
+// server ends up somewhere else. Other players are plain spheres moved towards where the
+// snapshots put them. Hits come back from the server as TargetHit, so hit markers and
+// stats work like on targets.
+//
+// Connect from the Multiplayer window (while paused) or with `--connect host[:port]`. The
+// same window can make the connection worse on purpose, see netsim.rs.
+pub struct NetClientPlugin;
+
+impl Plugin for NetClientPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<NetClient>()
+            .init_resource::<NetConditions>()
+            .init_resource::<Prediction>()
+            .add_event::<NetCommand>()
+            .add_systems(Startup, connect_on_launch)
+            // before player_input, so the view sent is the one its shots used
+            .add_systems(
+                PreUpdate,
+                (handle_net_commands, record_prediction, receive, send_input)
+                    .chain()
+                    .after(run_demo),
+            )
+            .add_systems(
+                Update,
+                (
+                    multiplayer_panel.run_if(game_paused).run_if(editor_closed),
+                    net_status,
+                    move_remote_players,
+                    smooth_corrections,
+                ),
+            );
+    }
+}
+
+#[derive(Event)]
+pub enum NetCommand {
+    Connect { address: String, name: String },
+    Disconnect,
+}
+
+#[derive(Default, Clone, Copy, PartialEq, Debug)]
+pub enum NetState {
+    #[default]
+    Offline,
+    Connecting,
+    Connected {
+        id: u16,
+    },
+}
+
+#[derive(Resource)]
+pub struct NetClient {
+    pub state: NetState,
+    // why the last connection ended, shown in the panel
+    pub status: String,
+    // newest snapshot from the server
+    pub snapshot: Snapshot,
+    socket: Option<NetSocket>,
+    // where the socket sends to, nobody else is listened to
+    server: Option<SocketAddr>,
+    last_heard: f64,
+    last_hello: f64,
+    name: String,
+    sequence: u32,
+    // the last few inputs, every packet carries all of them
+    sent: VecDeque<NetInput>,
+    // what's typed into the panel
+    address: String,
+}
+
+impl Default for NetClient {
+    fn default() -> Self {
+        Self {
+            state: NetState::Offline,
+            status: String::new(),
+            snapshot: Snapshot::default(),
+            socket: None,
+            server: None,
+            last_heard: 0.0,
+            last_hello: f64::NEG_INFINITY,
+            name: std::env::var("USER")
+                .or_else(|_| std::env::var("USERNAME"))
+                .unwrap_or_else(|_| "player".to_string()),
+            sequence: 0,
+            sent: VecDeque::new(),
+            address: format!("127.0.0.1:{DEFAULT_PORT}"),
+        }
+    }
+}
+
+impl NetClient {
+    fn send(&mut self, message: &ClientMessage) {
+        if let (Some(socket), Some(server)) = (&mut self.socket, self.server) {
+            // nothing to do about a lost packet, the next one repeats it
+            let _ = socket.send_to(&message.encode(), server);
+        }
+    }
+
+    fn disconnect(&mut self, status: impl Into<String>) {
+        if self.state != NetState::Offline {
+            self.send(&ClientMessage::Bye);
+        }
+        *self = NetClient {
+            status: status.into(),
+            name: std::mem::take(&mut self.name),
+            address: std::mem::take(&mut self.address),
+            ..default()
+        };
+    }
+}
+
+// another player, as far as the snapshots tell
+#[derive(Component)]
+pub struct RemotePlayer {
+    pub id: u16,
+    target: Vec3,
+}
+
+// --connect 127.0.0.1:27015 or --connect host, the port defaults to DEFAULT_PORT
+fn connect_on_launch(
+    options: Option<Res<LaunchOptions>>,
+    client: Res<NetClient>,
+    mut commands: EventWriter<NetCommand>,
+) {
+    if let Some(address) = options.and_then(|options| options.connect.clone()) {
+        commands.send(NetCommand::Connect {
+            address,
+            name: client.name.clone(),
+        });
+    }
+}
+
+fn resolve(address: &str) -> std::io::Result<SocketAddr> {
+    let with_port = if address.contains(':') {
+        address.to_string()
+    } else {
+        format!("{address}:{DEFAULT_PORT}")
+    };
+    with_port
+        .to_socket_addrs()?
+        .next()
+        .ok_or_else(|| std::io::Error::new(ErrorKind::NotFound, "no address found"))
+}
+
+fn open_socket(server: SocketAddr) -> std::io::Result<UdpSocket> {
+    let local = if server.is_ipv4() {
+        "0.0.0.0:0"
+    } else {
+        "[::]:0"
+    };
+    let socket = UdpSocket::bind(local)?;
+    socket.set_nonblocking(true)?;
+    Ok(socket)
+}
+
+fn handle_net_commands(
+    mut events: EventReader<NetCommand>,
+    mut client: ResMut<NetClient>,
+    time: Res<Time>,
+) {
+    for event in events.read() {
+        match event {
+            NetCommand::Connect { address, name } => {
+                client.disconnect("");
+                client.name = name.clone();
+                client.address = address.clone();
+                let opened = resolve(address)
+                    .and_then(|server| open_socket(server).map(|socket| (socket, server)));
+                match opened {
+                    Ok((socket, server)) => {
+                        info!("connecting to {address}");
+                        client.socket = Some(NetSocket::new(socket));
+                        client.server = Some(server);
+                        client.state = NetState::Connecting;
+                        client.last_heard = time.elapsed_seconds_f64();
+                    }
+                    Err(err) => client.status = format!("could not connect to {address}: {err}"),
+                }
+            }
+            NetCommand::Disconnect => client.disconnect("disconnected"),
+        }
+    }
+}
+
+// where the last input sent left us, now that the physics step has run it
+fn record_prediction(
+    client: Res<NetClient>,
+    mut prediction: ResMut<Prediction>,
+    player_q: Query<(&Transform, &Velocity, &Grounded), With<Player>>,
+) {
+    if !matches!(client.state, NetState::Connected { .. }) {
+        if !prediction.is_empty() {
+            prediction.clear();
+        }
+        return;
+    }
+    let Some(input) = client.sent.back().copied() else {
+        return;
+    };
+    for (transform, velocity, grounded) in player_q.iter() {
+        prediction.record(input, grounded.0, transform.translation, velocity.linvel);
+    }
+}
+
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+fn receive(
+    mut client: ResMut<NetClient>,
+    mut current: ResMut<CurrentMap>,
+    asset_server: Res<AssetServer>,
+    time: Res<Time>,
+    mut player_q: Query<(&mut Transform, &mut Velocity), With<Player>>,
+    mut prediction: ResMut<Prediction>,
+    movement: Res<Movement>,
+    conditions: Res<NetConditions>,
+    mut hits: EventWriter<TargetHit>,
+) {
+    let Some(server) = client.server else {
+        return;
+    };
+    let Some(socket) = client.socket.as_mut() else {
+        return;
+    };
+    socket.conditions = *conditions;
+    let now = time.elapsed_seconds_f64();
+
+    let mut messages = Vec::new();
+    let mut fresh = false;
+    let mut failed = None;
+    let mut buffer = [0; MAX_PACKET];
+    loop {
+        match socket.recv_from(&mut buffer) {
+            Ok((len, from)) if from == server => {
+                messages.extend(ServerMessage::decode(&buffer[..len]))
+            }
+            Ok(_) => continue,
+            Err(err) if err.kind() == ErrorKind::WouldBlock => break,
+            // nobody listening on that port (yet), keep saying hello
+            Err(err) if err.kind() == ErrorKind::ConnectionRefused => break,
+            Err(err) if err.kind() == ErrorKind::ConnectionReset => break,
+            Err(err) => {
+                failed = Some(err);
+                break;
+            }
+        }
+    }
+    if let Some(err) = failed {
+        client.disconnect(format!("connection lost: {err}"));
+        return;
+    }
+
+    for message in messages {
+        client.last_heard = now;
+        let state = client.state;
+        match message {
+            ServerMessage::Welcome { id, map } => {
+                if state == NetState::Connecting {
+                    info!("joined as player {id} on {map}");
+                    client.state = NetState::Connected { id };
+                    current.0 = asset_server.load(map);
+                }
+            }
+            ServerMessage::Refused(reason) => {
+                client.disconnect(format!("refused: {reason}"));
+                return;
+            }
+            ServerMessage::Snapshot(snapshot) => {
+                let NetState::Connected { id } = state else {
+                    continue;
+                };
+                // packets can arrive out of order or twice, an older one's hits are lost
+                // like a dropped one's so none are counted twice
+                if snapshot.tick > client.snapshot.tick {
+                    // only our own hits, they drive the hit markers and the session stats
+                    for hit in snapshot.hits.iter().filter(|hit| hit.shooter == id) {
+                        hits.send(TargetHit {
+                            point: hit.point,
+                            headshot: hit.headshot,
+                            kill: hit.kill,
+                        });
+                    }
+                    client.snapshot = snapshot;
+                    fresh = true;
+                }
+            }
+        }
+    }
+
+    let state = client.state;
+    if now - client.last_heard > TIMEOUT {
+        match state {
+            NetState::Connecting => client.disconnect("no answer from the server"),
+            _ => client.disconnect("lost connection to the server"),
+        }
+        return;
+    }
+    let NetState::Connected { id } = state else {
+        return;
+    };
+    if !fresh {
+        return;
+    }
+
+    // the server is in charge of where we are
+    let Some(me) = client
+        .snapshot
+        .players
+        .iter()
+        .find(|player| player.id == id)
+    else {
+        return;
+    };
+    for (mut transform, mut velocity) in player_q.iter_mut() {
+        prediction.reconcile(
+            client.snapshot.ack,
+            me.position,
+            me.velocity,
+            &movement,
+            &mut transform,
+            &mut velocity,
+        );
+    }
+}
+
+fn send_input(
+    mut client: ResMut<NetClient>,
+    input: Res<InputFrame>,
+    time: Res<Time>,
+    player_q: Query<&Paused, With<Player>>,
+    cam_q: Query<&Transform, (With<PlayerCamera>, Without<Player>)>,
+) {
+    let state = client.state;
+    match state {
+        NetState::Offline => {}
+        NetState::Connecting => {
+            let now = time.elapsed_seconds_f64();
+            if now - client.last_hello >= HELLO_INTERVAL {
+                client.last_hello = now;
+                let hello = ClientMessage::Hello {
+                    version: VERSION,
+                    name: client.name.clone(),
+                };
+                client.send(&hello);
+            }
+        }
+        NetState::Connected { .. } => {
+            let Ok(cam) = cam_q.get_single() else {
+                return;
+            };
+            // the menus are up, the server shouldn't see any of those clicks
+            let frame = if player_q.iter().any(|paused| paused.0) {
+                InputFrame {
+                    dt: input.dt,
+                    ..default()
+                }
+            } else {
+                *input
+            };
+
+            client.sequence += 1;
+            let sequence = client.sequence;
+            let seen = client.snapshot.tick.saturating_sub(REMOTE_DELAY);
+            client.sent.push_back(NetInput {
+                sequence,
+                frame,
+                view: view_angles(cam.rotation),
+                seen,
+            });
+            while client.sent.len() > INPUT_REDUNDANCY {
+                client.sent.pop_front();
+            }
+            let inputs = ClientMessage::Input(client.sent.iter().copied().collect());
+            client.send(&inputs);
+        }
+    }
+}
+
+// how quickly remote players catch up with their latest position, per second
+const REMOTE_SMOOTHING: f32 = 20.0;
+// so they trail the newest snapshot by about this many ticks
+const REMOTE_DELAY: u32 = (TICK_RATE / REMOTE_SMOOTHING as f64) as u32;
+
+fn move_remote_players(
+    mut commands: Commands,
+    client: Res<NetClient>,
+    mut remote_q: Query<(Entity, &mut RemotePlayer, &mut Transform)>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    time: Res<Time>,
+) {
+    let others: Vec<_> = match client.state {
+        NetState::Connected { id } => client
+            .snapshot
+            .players
+            .iter()
+            .filter(|state| state.id != id)
+            .collect(),
+        _ => Vec::new(),
+    };
+
+    for (entity, mut remote, mut transform) in remote_q.iter_mut() {
+        match others.iter().find(|state| state.id == remote.id) {
+            Some(state) => {
+                remote.target = state.position;
+                transform.rotation = view_rotation(Vec2::new(state.view.x, 0.0));
+            }
+            None => commands.entity(entity).despawn_recursive(),
+        }
+        let smoothing = 1.0 - (-REMOTE_SMOOTHING * time.delta_seconds()).exp();
+        transform.translation = transform.translation.lerp(remote.target, smoothing);
+    }
+
+    for state in others {
+        if remote_q.iter().any(|(_, remote, _)| remote.id == state.id) {
+            continue;
+        }
+        commands.spawn((
+            PbrBundle {
+                mesh: meshes.add(Mesh::from(shape::UVSphere {
+                    radius: 0.5,
+                    ..default()
+                })),
+                material: materials.add(Color::ORANGE_RED.into()),
+                transform: Transform::from_translation(state.position),
+                ..default()
+            },
+            RemotePlayer {
+                id: state.id,
+                target: state.position,
+            },
+        ));
+    }
+}
+
+// health and score in the corner while playing online
+fn net_status(mut contexts: EguiContexts, client: Res<NetClient>) {
+    let NetState::Connected { id } = client.state else {
+        return;
+    };
+    let Some(me) = client.snapshot.players.iter().find(|state| state.id == id) else {
+        return;
+    };
+
+    egui::Area::new("net status")
+        .anchor(egui::Align2::CENTER_TOP, egui::vec2(0.0, 10.0))
+        .show(contexts.ctx_mut(), |ui| {
+            ui.label(
+                egui::RichText::new(format!(
+                    "HP {}   {} kills / {} deaths",
+                    me.health, me.kills, me.deaths
+                ))
+                .size(20.0)
+                .color(egui::Color32::WHITE),
+            );
+        });
+}
+
+fn multiplayer_panel(
+    mut contexts: EguiContexts,
+    mut client: ResMut<NetClient>,
+    prediction: Res<Prediction>,
+    mut conditions: ResMut<NetConditions>,
+    mut commands: EventWriter<NetCommand>,
+) {
+    let state = client.state;
+    egui::Window::new("Multiplayer").show(contexts.ctx_mut(), |ui| {
+        match state {
+            NetState::Offline => {
+                ui.horizontal(|ui| {
+                    ui.label("Server");
+                    ui.text_edit_singleline(&mut client.address);
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Name");
+                    ui.text_edit_singleline(&mut client.name);
+                });
+                if ui.button("Connect").clicked() {
+                    commands.send(NetCommand::Connect {
+                        address: client.address.clone(),
+                        name: client.name.clone(),
+                    });
+                }
+                if !client.status.is_empty() {
+                    ui.label(&client.status);
+                }
+            }
+            NetState::Connecting => {
+                ui.label(format!("Connecting to {}...", client.address));
+                if ui.button("Cancel").clicked() {
+                    commands.send(NetCommand::Disconnect);
+                }
+            }
+            NetState::Connected { id } => {
+                ui.label(format!("Playing on {}", client.address));
+                ui.label(format!(
+                    "{} corrections, last off by {:.2}",
+                    prediction.corrections, prediction.last_error
+                ));
+                egui::Grid::new("scoreboard").show(ui, |ui| {
+                    ui.strong("Name");
+                    ui.strong("Kills");
+                    ui.strong("Deaths");
+                    ui.end_row();
+
+                    let mut players = client.snapshot.players.clone();
+                    players.sort_by(|a, b| b.kills.cmp(&a.kills).then(a.deaths.cmp(&b.deaths)));
+                    for player in players {
+                        if player.id == id {
+                            ui.strong(&player.name);
+                        } else {
+                            ui.label(&player.name);
+                        }
+                        ui.label(player.kills.to_string());
+                        ui.label(player.deaths.to_string());
+                        ui.end_row();
+                    }
+                });
+                if ui.button("Disconnect").clicked() {
+                    commands.send(NetCommand::Disconnect);
+                }
+            }
+        }
+
+        // only our side of the connection, the server has its own flags
+        let mut edited = *conditions;
+        ui.collapsing("Simulated network", |ui| {
+            egui::Grid::new("net conditions").show(ui, |ui| {
+                ui.label("Latency");
+                ui.add(egui::Slider::new(&mut edited.latency, 0.0..=300.0).suffix(" ms"));
+                ui.end_row();
+                ui.label("Jitter");
+                ui.add(egui::Slider::new(&mut edited.jitter, 0.0..=100.0).suffix(" ms"));
+                ui.end_row();
+                ui.label("Loss");
+                ui.add(egui::Slider::new(&mut edited.loss, 0.0..=50.0).suffix(" %"));
+                ui.end_row();
+                ui.label("Duplicates");
+                ui.add(egui::Slider::new(&mut edited.duplicate, 0.0..=50.0).suffix(" %"));
+                ui.end_row();
+                ui.label("Reordered");
+                ui.add(egui::Slider::new(&mut edited.reorder, 0.0..=50.0).suffix(" %"));
+                ui.end_row();
+            });
+            if ui.button("Perfect").clicked() {
+                edited = NetConditions::default();
+            }
+        });
+        if edited != *conditions {
+            *conditions = edited;
+        }
+    });
+}
@@ -0,0 +1,607 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+use bevy_egui::{
+    egui::{
+        self,
+        text::{CCursor, CCursorRange},
+    },
+    EguiContexts,
+};
+use bevy_rapier3d::prelude::*;
+
+use crate::{
+    arena::{generate, ArenaParams},
+    demo::{run_demo, DemoCommand},
+    input::{gather_input, InputFrame, BACK, FORWARD, JUMP, LEFT, RIGHT, SPRINT},
+    map::Map,
+    player::{
+        player_input, Ammo, Movement, Player, PlayerCamera, RocketCooldown, Sensitivity,
+        ShootCooldown,
+    },
+    world::CurrentMap,
+};
+
+// Quake style drop-down console, ` (the key under Esc) opens and closes it. A line is a
+// variable or a command and its arguments: `sv_gravity` prints the gravity, `sv_gravity 20`
+// sets it and `help` lists everything. Tab completes names, up and down go through the
+// lines entered before. While it's open the keys only type.
+//
+// Variables and commands are the CVARS and COMMANDS tables below. Changes last until the
+// game closes, and only change the local game, a server keeps its own.
+pub struct ConsolePlugin;
+
+impl Plugin for ConsolePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Console>()
+            .add_systems(
+                PreUpdate,
+                hold_player
+                    .run_if(console_open)
+                    .after(gather_input)
+                    .before(run_demo),
+            )
+            .add_systems(
+                Update,
+                (
+                    toggle_console,
+                    console_window.run_if(console_open),
+                    run_lines,
+                )
+                    .chain(),
+            )
+            .add_systems(Update, fly.after(player_input));
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct Console {
+    pub open: bool,
+    // what's being typed
+    input: String,
+    // oldest first
+    log: Vec<String>,
+    // lines entered, oldest first
+    history: Vec<String>,
+    // which history line up/down got to, None while typing a new one
+    browsing: Option<usize>,
+    // entered this frame, run_lines runs them
+    pending: Vec<String>,
+}
+
+// no ConsolePlugin (headless) counts as closed
+pub fn console_open(console: Option<Res<Console>>) -> bool {
+    console.is_some_and(|console| console.open)
+}
+
+// lines kept in the log
+const MAX_LOG: usize = 500;
+const HEIGHT: f32 = 300.0;
+// noclip flies this fast, three times that sprinting
+const NOCLIP_SPEED: f32 = 10.0;
+
+impl Console {
+    pub fn print(&mut self, text: impl AsRef<str>) {
+        self.log.extend(text.as_ref().lines().map(str::to_string));
+        if self.log.len() > MAX_LOG {
+            self.log.drain(..self.log.len() - MAX_LOG);
+        }
+    }
+
+    fn submit(&mut self) {
+        let line = std::mem::take(&mut self.input).trim().to_string();
+        self.browsing = None;
+        if line.is_empty() {
+            return;
+        }
+        self.print(format!("] {line}"));
+        if self.history.last() != Some(&line) {
+            self.history.push(line.clone());
+        }
+        self.pending.push(line);
+    }
+
+    fn browse(&mut self, older: bool) {
+        let last = match self.history.len() {
+            0 => return,
+            len => len - 1,
+        };
+        self.browsing = match (self.browsing, older) {
+            (None, true) => Some(last),
+            (None, false) => return,
+            (Some(index), true) => Some(index.saturating_sub(1)),
+            // past the newest is an empty line again
+            (Some(index), false) => (index < last).then_some(index + 1),
+        };
+        self.input = self
+            .browsing
+            .map_or_else(String::new, |index| self.history[index].clone());
+    }
+
+    // only the name, arguments are up to the command
+    fn complete(&mut self) {
+        let typed = self.input.trim_start();
+        if typed.contains(' ') {
+            return;
+        }
+        let mut matches: Vec<&str> = CVARS
+            .iter()
+            .map(|cvar| cvar.name)
+            .chain(COMMANDS.iter().map(|command| command.name))
+            .filter(|name| name.starts_with(typed))
+            .collect();
+        matches.sort();
+
+        match matches.as_slice() {
+            [] => {}
+            [only] => self.input = format!("{only} "),
+            [first, rest @ ..] => {
+                // as far as they all agree, then show what's left to pick from
+                let common = rest.iter().fold(first.len(), |len, name| {
+                    first
+                        .bytes()
+                        .zip(name.bytes())
+                        .take(len)
+                        .take_while(|(a, b)| a == b)
+                        .count()
+                });
+                self.input = first[..common].to_string();
+                let list = matches.join("  ");
+                self.print(list);
+            }
+        }
+    }
+}
+
+// the keys type into the console now, like the spectator's hold_player
+fn hold_player(mut input: ResMut<InputFrame>) {
+    *input = InputFrame {
+        dt: input.dt,
+        ..default()
+    };
+}
+
+fn toggle_console(keys: Res<Input<KeyCode>>, mut console: ResMut<Console>) {
+    if keys.just_pressed(KeyCode::Grave) || (console.open && keys.just_pressed(KeyCode::Escape)) {
+        console.open = !console.open;
+    }
+}
+
+fn console_window(mut contexts: EguiContexts, mut console: ResMut<Console>) {
+    let console = &mut *console;
+    egui::TopBottomPanel::top("console")
+        .exact_height(HEIGHT)
+        .show(contexts.ctx_mut(), |ui| {
+            let input_height = ui.spacing().interact_size.y + ui.spacing().item_spacing.y * 2.0;
+            egui::ScrollArea::vertical()
+                .max_height(ui.available_height() - input_height)
+                .auto_shrink([false; 2])
+                .stick_to_bottom(true)
+                .show(ui, |ui| {
+                    for line in console.log.iter() {
+                        ui.monospace(line);
+                    }
+                });
+            ui.separator();
+
+            // taken before the text box gets to see them
+            let (up, down, tab) = ui.input_mut(|input| {
+                (
+                    input.consume_key(egui::Modifiers::NONE, egui::Key::ArrowUp),
+                    input.consume_key(egui::Modifiers::NONE, egui::Key::ArrowDown),
+                    input.consume_key(egui::Modifiers::NONE, egui::Key::Tab),
+                )
+            });
+            if up || down {
+                console.browse(up);
+            }
+            if tab {
+                console.complete();
+            }
+
+            let mut output = egui::TextEdit::singleline(&mut console.input)
+                .font(egui::TextStyle::Monospace)
+                .desired_width(f32::INFINITY)
+                .lock_focus(true)
+                .show(ui);
+            // the key that opens the console types itself too
+            console.input.retain(|c| c != '`' && c != '~');
+
+            if output.response.lost_focus() && ui.input(|input| input.key_pressed(egui::Key::Enter))
+            {
+                console.submit();
+            }
+            output.response.request_focus();
+
+            if up || down || tab {
+                // cursor to the end of what was filled in
+                let end = CCursor::new(console.input.chars().count());
+                output.state.set_ccursor_range(Some(CCursorRange::one(end)));
+                output.state.store(ui.ctx(), output.response.id);
+            }
+        });
+}
+
+// needs the whole world since a line can touch anything
+fn run_lines(world: &mut World) {
+    let lines = std::mem::take(&mut world.resource_mut::<Console>().pending);
+    for line in lines {
+        let output = run_line(world, &line);
+        world.resource_mut::<Console>().print(output);
+    }
+}
+
+fn run_line(world: &mut World, line: &str) -> String {
+    let mut words = line.split_whitespace();
+    let Some(name) = words.next() else {
+        return String::new();
+    };
+    let args: Vec<&str> = words.collect();
+
+    if let Some(cvar) = CVARS.iter().find(|cvar| cvar.name == name) {
+        return match args.as_slice() {
+            [] => match (cvar.get)(world) {
+                Some(value) => format!("{name} is {value}"),
+                None => NO_PLAYER.to_string(),
+            },
+            [value] => match value.parse::<f32>() {
+                Ok(value) if value.is_finite() => match (cvar.set)(world, value) {
+                    Ok(()) => String::new(),
+                    Err(err) => err,
+                },
+                _ => format!("{name} needs a number"),
+            },
+            _ => format!("usage: {name} [value]"),
+        };
+    }
+
+    match COMMANDS.iter().find(|command| command.name == name) {
+        Some(command) => (command.run)(world, &args),
+        None => format!("unknown command '{name}', try help"),
+    }
+}
+
+const NO_PLAYER: &str = "there's no player right now";
+
+struct Cvar {
+    name: &'static str,
+    help: &'static str,
+    // None when what it's stored on isn't there
+    get: fn(&mut World) -> Option<f32>,
+    // what to print when it can't be set
+    set: fn(&mut World, f32) -> Result<(), String>,
+}
+
+struct Command {
+    name: &'static str,
+    usage: &'static str,
+    help: &'static str,
+    // what to print
+    run: fn(&mut World, &[&str]) -> String,
+}
+
+const CVARS: &[Cvar] = &[
+    Cvar {
+        name: "sv_gravity",
+        help: "downward pull, m/s²",
+        get: |world| Some(-world.resource::<RapierConfiguration>().gravity.y),
+        set: |world, value| {
+            world.resource_mut::<RapierConfiguration>().gravity = Vec3::NEG_Y * value;
+            Ok(())
+        },
+    },
+    Cvar {
+        name: "sv_walkspeed",
+        help: "running speed, m/s",
+        get: |world| Some(world.resource::<Movement>().walk_speed),
+        set: |world, value| {
+            world.resource_mut::<Movement>().walk_speed = value;
+            Ok(())
+        },
+    },
+    Cvar {
+        name: "sv_sprintspeed",
+        help: "sprinting speed, m/s",
+        get: |world| Some(world.resource::<Movement>().sprint_speed),
+        set: |world, value| {
+            world.resource_mut::<Movement>().sprint_speed = value;
+            Ok(())
+        },
+    },
+    Cvar {
+        name: "sv_jumpspeed",
+        help: "upward speed of a jump, m/s",
+        get: |world| Some(world.resource::<Movement>().jump_speed),
+        set: |world, value| {
+            world.resource_mut::<Movement>().jump_speed = value;
+            Ok(())
+        },
+    },
+    Cvar {
+        name: "sv_shootcooldown",
+        help: "seconds between shots",
+        get: |world| {
+            Some(
+                player::<ShootCooldown>(world)?
+                    .timer
+                    .duration()
+                    .as_secs_f32(),
+            )
+        },
+        set: |world, value| {
+            set_cooldown(
+                &mut player::<ShootCooldown>(world).ok_or(NO_PLAYER)?.timer,
+                value,
+            )
+        },
+    },
+    Cvar {
+        name: "sv_rocketcooldown",
+        help: "seconds between rockets",
+        get: |world| {
+            Some(
+                player::<RocketCooldown>(world)?
+                    .timer
+                    .duration()
+                    .as_secs_f32(),
+            )
+        },
+        set: |world, value| {
+            set_cooldown(
+                &mut player::<RocketCooldown>(world).ok_or(NO_PLAYER)?.timer,
+                value,
+            )
+        },
+    },
+    Cvar {
+        name: "sv_magazine",
+        help: "rounds per magazine, 0 for unlimited ammo",
+        get: |world| Some(player::<Ammo>(world)?.capacity.unwrap_or(0) as f32),
+        set: |world, value| {
+            *player::<Ammo>(world).ok_or(NO_PLAYER)? = if value >= 1.0 {
+                Ammo::magazine(value as u32)
+            } else {
+                Ammo::default()
+            };
+            Ok(())
+        },
+    },
+    Cvar {
+        name: "cl_sensitivity",
+        help: "degrees per mouse count",
+        get: |world| Some(player::<Sensitivity>(world)?.0),
+        set: |world, value| {
+            player::<Sensitivity>(world).ok_or(NO_PLAYER)?.0 = value;
+            Ok(())
+        },
+    },
+    Cvar {
+        name: "cl_fov",
+        help: "vertical field of view, degrees",
+        get: |world| match *player_camera::<Projection>(world)? {
+            Projection::Perspective(ref perspective) => Some(perspective.fov.to_degrees()),
+            Projection::Orthographic(_) => None,
+        },
+        set: |world, value| {
+            if let Projection::Perspective(perspective) = player_camera::<Projection>(world)
+                .ok_or(NO_PLAYER)?
+                .as_mut()
+            {
+                perspective.fov = value.clamp(1.0, 179.0).to_radians();
+            }
+            Ok(())
+        },
+    },
+];
+
+const COMMANDS: &[Command] = &[
+    Command {
+        name: "help",
+        usage: "help",
+        help: "list variables and commands",
+        run: help,
+    },
+    Command {
+        name: "clear",
+        usage: "clear",
+        help: "empty the console",
+        run: |world, _| {
+            world.resource_mut::<Console>().log.clear();
+            String::new()
+        },
+    },
+    Command {
+        name: "map",
+        usage: "map [path | arena [seed]]",
+        help: "load a map file or generate an arena, no argument prints the current map",
+        run: map,
+    },
+    Command {
+        name: "noclip",
+        usage: "noclip",
+        help: "fly through walls, again to land",
+        run: noclip,
+    },
+    Command {
+        name: "give",
+        usage: "give ammo [rounds] | rocket",
+        help: "fill the magazine (or add rounds), or skip the rocket cooldown",
+        run: give,
+    },
+    Command {
+        name: "record",
+        usage: "record [stop]",
+        help: "start recording a demo, or stop and save it",
+        run: record,
+    },
+];
+
+// the local player's T
+fn player<T: Component>(world: &mut World) -> Option<Mut<'_, T>> {
+    world
+        .query_filtered::<&mut T, With<Player>>()
+        .get_single_mut(world)
+        .ok()
+}
+
+fn player_camera<T: Component>(world: &mut World) -> Option<Mut<'_, T>> {
+    world
+        .query_filtered::<&mut T, With<PlayerCamera>>()
+        .get_single_mut(world)
+        .ok()
+}
+
+fn set_cooldown(timer: &mut Timer, seconds: f32) -> Result<(), String> {
+    let duration = Duration::try_from_secs_f32(seconds.max(0.0))
+        .map_err(|_| format!("{seconds} seconds is too long"))?;
+    timer.set_duration(duration);
+    Ok(())
+}
+
+fn help(_world: &mut World, _args: &[&str]) -> String {
+    let mut lines = vec!["variables, `name value` sets one:".to_string()];
+    for cvar in CVARS {
+        lines.push(format!("  {:<28}{}", cvar.name, cvar.help));
+    }
+    lines.push("commands:".to_string());
+    for command in COMMANDS {
+        lines.push(format!("  {:<28}{}", command.usage, command.help));
+    }
+    lines.join("\n")
+}
+
+// swapping the handle makes world.rs build the new map
+fn map(world: &mut World, args: &[&str]) -> String {
+    let handle = match args {
+        [] => {
+            let id = world.resource::<CurrentMap>().0.id();
+            return match world.resource::<Assets<Map>>().get(id) {
+                Some(map) => format!("on {}", map.name),
+                None => "the map is still loading".to_string(),
+            };
+        }
+        ["arena"] | ["arena", _] => {
+            let seed = match args.get(1).map(|seed| seed.parse()) {
+                Some(Ok(seed)) => seed,
+                Some(Err(_)) => return "the seed has to be a number".to_string(),
+                None => fastrand::u64(..),
+            };
+            let mut params = world
+                .get_resource::<ArenaParams>()
+                .cloned()
+                .unwrap_or_default();
+            params.seed = seed;
+            let handle = world.resource_mut::<Assets<Map>>().add(generate(&params));
+            // the Arena generator window follows along
+            if let Some(mut current) = world.get_resource_mut::<ArenaParams>() {
+                *current = params;
+            }
+            handle
+        }
+        [path] => world.resource::<AssetServer>().load(path.to_string()),
+        _ => return "usage: map [path | arena [seed]]".to_string(),
+    };
+    world.resource_mut::<CurrentMap>().0 = handle;
+    format!("loading {}", args.join(" "))
+}
+
+// on the local player while noclipping, fly moves it
+#[derive(Component)]
+struct Noclip;
+
+// a velocity based kinematic body goes through everything and ignores gravity
+fn noclip(world: &mut World, _args: &[&str]) -> String {
+    let Ok((entity, noclipping)) = world
+        .query_filtered::<(Entity, Has<Noclip>), With<Player>>()
+        .get_single(world)
+    else {
+        return NO_PLAYER.to_string();
+    };
+
+    let mut player = world.entity_mut(entity);
+    if noclipping {
+        player.remove::<Noclip>().insert(RigidBody::Dynamic);
+        "noclip off".to_string()
+    } else {
+        player.insert((Noclip, RigidBody::KinematicVelocityBased));
+        "noclip on".to_string()
+    }
+}
+
+fn give(world: &mut World, args: &[&str]) -> String {
+    match args {
+        ["ammo"] | ["ammo", _] => {
+            let rounds = match args.get(1).map(|rounds| rounds.parse::<u32>()) {
+                Some(Ok(rounds)) => Some(rounds),
+                Some(Err(_)) => return "rounds has to be a number".to_string(),
+                None => None,
+            };
+            let Some(mut ammo) = player::<Ammo>(world) else {
+                return NO_PLAYER.to_string();
+            };
+            let Some(capacity) = ammo.capacity else {
+                return "ammo is unlimited, sv_magazine sets a magazine size".to_string();
+            };
+            // going over the capacity is fine, the next reload brings it back
+            ammo.loaded = rounds.map_or(capacity, |rounds| ammo.loaded.saturating_add(rounds));
+            ammo.reloading = false;
+            format!("{} rounds loaded", ammo.loaded)
+        }
+        ["rocket"] => {
+            let Some(mut cooldown) = player::<RocketCooldown>(world) else {
+                return NO_PLAYER.to_string();
+            };
+            let duration = cooldown.timer.duration();
+            cooldown.timer.set_elapsed(duration);
+            "rocket ready".to_string()
+        }
+        _ => "usage: give ammo [rounds] | rocket".to_string(),
+    }
+}
+
+fn record(world: &mut World, args: &[&str]) -> String {
+    match args {
+        [] => {
+            world.send_event(DemoCommand::Record);
+            "recording, `record stop` saves it".to_string()
+        }
+        ["stop"] => {
+            world.send_event(DemoCommand::Stop);
+            String::new()
+        }
+        _ => "usage: record [stop]".to_string(),
+    }
+}
+
+// noclip flying, where the camera looks
+fn fly(
+    input: Res<InputFrame>,
+    mut player_q: Query<&mut Velocity, (With<Player>, With<Noclip>)>,
+    cam_q: Query<&Transform, With<PlayerCamera>>,
+) {
+    let Ok(cam) = cam_q.get_single() else {
+        return;
+    };
+
+    for mut velocity in player_q.iter_mut() {
+        let mut direction = Vec3::ZERO;
+        if input.held(FORWARD) {
+            direction += cam.forward();
+        }
+        if input.held(BACK) {
+            direction += cam.back();
+        }
+        if input.held(LEFT) {
+            direction += cam.left();
+        }
+        if input.held(RIGHT) {
+            direction += cam.right();
+        }
+        if input.held(JUMP) {
+            direction += Vec3::Y;
+        }
+
+        let boost = if input.held(SPRINT) { 3.0 } else { 1.0 };
+        velocity.linvel = direction.normalize_or_zero() * NOCLIP_SPEED * boost;
+    }
+}
@@ -0,0 +1,488 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use bevy::prelude::*;
+
+use crate::{
+    map::{Map, TriggerKind},
+    pads::TriggerEntered,
+    player::Player,
+    rng::GameRng,
+    world::{CurrentMap, MapLoaded},
+};
+
+// Time trials: touching a CourseStart trigger starts the clock, Checkpoint triggers record
+// splits (in order), CourseFinish stops it. The best run per map is kept as a ghost in
+// ghosts/<map name>.ghost and replayed next to the player on every attempt.
+pub struct CoursePlugin;
+
+impl Plugin for CoursePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Course>()
+            .add_systems(Startup, (spawn_timer_text, spawn_ghost))
+            .add_systems(
+                Update,
+                (reset_course, run_course, show_timer, replay_ghost).chain(),
+            )
+            .add_systems(FixedUpdate, record_ghost);
+    }
+}
+
+#[derive(Clone, Copy)]
+struct GhostFrame {
+    time: f32,
+    position: Vec3,
+    rotation: Quat,
+}
+
+#[derive(Clone, Default)]
+struct Run {
+    time: f32,
+    splits: Vec<f32>,
+    frames: Vec<GhostFrame>,
+}
+
+#[derive(Resource, Default)]
+struct Course {
+    map_name: String,
+    checkpoints: u32,
+    // elapsed seconds when the current run started
+    started: Option<f64>,
+    next_checkpoint: u32,
+    current: Run,
+    last: Option<Run>,
+    best: Option<Run>,
+}
+
+impl Course {
+    fn run_time(&self, now: f64) -> Option<f32> {
+        self.started.map(|started| (now - started) as f32)
+    }
+}
+
+#[derive(Component)]
+struct TimerText;
+
+#[derive(Component)]
+struct Ghost;
+
+fn spawn_timer_text(mut commands: Commands) {
+    let container = NodeBundle {
+        style: Style {
+            width: Val::Percent(100.0),
+            position_type: PositionType::Absolute,
+            top: Val::Px(20.0),
+            justify_content: JustifyContent::Center,
+            ..default()
+        },
+        ..default()
+    };
+
+    let text = (
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font_size: 32.0,
+                color: Color::WHITE,
+                ..default()
+            },
+        )
+        .with_text_alignment(TextAlignment::Center),
+        TimerText,
+    );
+
+    commands.spawn(container).with_children(|parent| {
+        parent.spawn(text);
+    });
+}
+
+fn spawn_ghost(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    let material = materials.add(StandardMaterial {
+        base_color: Color::rgba(0.6, 0.9, 1.0, 0.3),
+        alpha_mode: AlphaMode::Blend,
+        unlit: true,
+        ..default()
+    });
+
+    let ghost = (
+        PbrBundle {
+            mesh: meshes.add(Mesh::from(shape::UVSphere {
+                radius: 0.5,
+                ..default()
+            })),
+            material: material.clone(),
+            visibility: Visibility::Hidden,
+            ..default()
+        },
+        Ghost,
+    );
+
+    // little nub so you can see where the ghost was looking
+    let nose = PbrBundle {
+        mesh: meshes.add(Mesh::from(shape::Box::new(0.15, 0.15, 0.4))),
+        material,
+        transform: Transform::from_xyz(0.0, 0.0, -0.55),
+        ..default()
+    };
+
+    commands.spawn(ghost).with_children(|parent| {
+        parent.spawn(nose);
+    });
+}
+
+fn reset_course(
+    mut events: EventReader<MapLoaded>,
+    mut course: ResMut<Course>,
+    current: Res<CurrentMap>,
+    maps: Res<Assets<Map>>,
+) {
+    if events.read().next().is_none() {
+        return;
+    }
+    let Some(map) = maps.get(current.0.id()) else {
+        return;
+    };
+
+    let map_name = if map.name.is_empty() {
+        "unnamed".to_string()
+    } else {
+        map.name.clone()
+    };
+    let checkpoints = map
+        .triggers
+        .iter()
+        .filter(|trigger| matches!(trigger.kind, TriggerKind::Checkpoint { .. }))
+        .count() as u32;
+
+    // keep the best run when the same map is just hot reloaded
+    let best = if map_name == course.map_name {
+        course.best.take()
+    } else {
+        load_ghost(&ghost_path(&map_name))
+    };
+
+    *course = Course {
+        map_name,
+        checkpoints,
+        best,
+        ..default()
+    };
+}
+
+fn run_course(
+    mut events: EventReader<TriggerEntered>,
+    mut course: ResMut<Course>,
+    time: Res<Time>,
+) {
+    let now = time.elapsed_seconds_f64();
+
+    for TriggerEntered(kind) in events.read() {
+        match kind {
+            TriggerKind::CourseStart => {
+                course.started = Some(now);
+                course.next_checkpoint = 0;
+                course.current = Run::default();
+            }
+            TriggerKind::Checkpoint { order } => {
+                if *order != course.next_checkpoint {
+                    continue;
+                }
+                if let Some(split) = course.run_time(now) {
+                    course.current.splits.push(split);
+                    course.next_checkpoint += 1;
+                }
+            }
+            TriggerKind::CourseFinish => {
+                if course.next_checkpoint < course.checkpoints {
+                    continue;
+                }
+                let Some(run_time) = course.run_time(now) else {
+                    continue;
+                };
+
+                course.started = None;
+                let mut run = std::mem::take(&mut course.current);
+                run.time = run_time;
+
+                if course.best.as_ref().is_none_or(|best| run.time < best.time) {
+                    if let Err(err) = save_ghost(&ghost_path(&course.map_name), &run) {
+                        warn!("could not save ghost: {err}");
+                    }
+                    course.best = Some(run.clone());
+                }
+                course.last = Some(run);
+            }
+            _ => {}
+        }
+    }
+}
+
+fn record_ghost(
+    mut course: ResMut<Course>,
+    time: Res<Time>,
+    player_q: Query<(Entity, &Transform), With<Player>>,
+    cam_q: Query<(&Parent, &Transform), With<Camera3d>>,
+) {
+    let Some(run_time) = course.run_time(time.elapsed_seconds_f64()) else {
+        return;
+    };
+
+    for (player_entity, player_transform) in player_q.iter() {
+        let rotation = cam_q
+            .iter()
+            .find(|(parent, _)| parent.get() == player_entity)
+            .map(|(_, cam)| cam.rotation)
+            .unwrap_or_default();
+
+        course.current.frames.push(GhostFrame {
+            time: run_time.max(0.0),
+            position: player_transform.translation,
+            rotation,
+        });
+    }
+}
+
+fn replay_ghost(
+    course: Res<Course>,
+    time: Res<Time>,
+    mut ghost_q: Query<(&mut Transform, &mut Visibility), With<Ghost>>,
+) {
+    let run_time = course.run_time(time.elapsed_seconds_f64());
+    let frames = course.best.as_ref().map(|best| &best.frames[..]);
+
+    for (mut transform, mut visibility) in ghost_q.iter_mut() {
+        let (Some(run_time), Some(frames)) = (run_time, frames) else {
+            *visibility = Visibility::Hidden;
+            continue;
+        };
+        let Some((position, rotation)) = sample(frames, run_time) else {
+            *visibility = Visibility::Hidden;
+            continue;
+        };
+
+        *visibility = Visibility::Visible;
+        transform.translation = position;
+        transform.rotation = rotation;
+    }
+}
+
+// interpolates between the two recorded ticks around `time`
+fn sample(frames: &[GhostFrame], time: f32) -> Option<(Vec3, Quat)> {
+    let next = frames.partition_point(|frame| frame.time < time);
+    let after = frames.get(next).or(frames.last())?;
+    let before = frames.get(next.saturating_sub(1)).unwrap_or(after);
+
+    let span = after.time - before.time;
+    let t = if span > 0.0 {
+        ((time - before.time) / span).clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+
+    Some((
+        before.position.lerp(after.position, t),
+        before.rotation.slerp(after.rotation, t),
+    ))
+}
+
+fn show_timer(
+    course: Res<Course>,
+    time: Res<Time>,
+    rng: Res<GameRng>,
+    mut text_q: Query<&mut Text, With<TimerText>>,
+) {
+    let run_time = course.run_time(time.elapsed_seconds_f64());
+    let best_splits = course.best.as_ref().map(|best| &best.splits[..]);
+
+    let mut lines = Vec::new();
+
+    match (run_time, &course.last) {
+        (Some(run_time), _) => {
+            lines.push(format_time(run_time));
+            for (index, split) in course.current.splits.iter().enumerate() {
+                lines.push(split_line(index, *split, best_splits));
+            }
+        }
+        (None, Some(last)) => {
+            let mut finish = format!("FINISH {}", format_time(last.time));
+            if let Some(best) = &course.best {
+                finish.push_str(&format!("  (best {})", format_time(best.time)));
+            }
+            lines.push(finish);
+            for (index, split) in last.splits.iter().enumerate() {
+                lines.push(split_line(index, *split, best_splits));
+            }
+            // same seed means the same targets, so runs are only comparable with it
+            lines.push(format!("seed {}", rng.seed()));
+        }
+        (None, None) => {}
+    }
+
+    for mut text in text_q.iter_mut() {
+        text.sections[0].value = lines.join("\n");
+    }
+}
+
+fn split_line(index: usize, split: f32, best_splits: Option<&[f32]>) -> String {
+    let mut line = format!("CP{} {}", index + 1, format_time(split));
+    if let Some(best) = best_splits.and_then(|splits| splits.get(index)) {
+        let delta = split - best;
+        let sign = if delta < 0.0 { '-' } else { '+' };
+        line.push_str(&format!(" ({sign}{:.3})", delta.abs()));
+    }
+    line
+}
+
+fn format_time(seconds: f32) -> String {
+    let minutes = (seconds / 60.0).floor() as u32;
+    format!("{}:{:06.3}", minutes, seconds - minutes as f32 * 60.0)
+}
+
+// map names come from shared files, so anything that could leave ghosts/ is replaced
+fn ghost_path(map_name: &str) -> PathBuf {
+    let stem: String = map_name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    let stem = if stem.is_empty() { "unnamed" } else { &stem };
+    PathBuf::from("ghosts").join(format!("{stem}.ghost"))
+}
+
+// little endian: magic, run time, splits, then time + position + rotation per tick
+fn save_ghost(path: &Path, run: &Run) -> io::Result<()> {
+    let mut bytes = Vec::with_capacity(16 + run.splits.len() * 4 + run.frames.len() * 32);
+    bytes.extend_from_slice(b"GHST");
+    bytes.extend_from_slice(&run.time.to_le_bytes());
+    bytes.extend_from_slice(&(run.splits.len() as u32).to_le_bytes());
+    for split in run.splits.iter() {
+        bytes.extend_from_slice(&split.to_le_bytes());
+    }
+    bytes.extend_from_slice(&(run.frames.len() as u32).to_le_bytes());
+    for frame in run.frames.iter() {
+        let values = [frame.time]
+            .into_iter()
+            .chain(frame.position.to_array())
+            .chain(frame.rotation.to_array());
+        for value in values {
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+    }
+
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(path, bytes)
+}
+
+fn load_ghost(path: &Path) -> Option<Run> {
+    let bytes = fs::read(path).ok()?;
+    let mut rest = bytes.strip_prefix(b"GHST")?;
+    let mut next = || -> Option<[u8; 4]> {
+        let (value, tail) = rest.split_first_chunk::<4>()?;
+        rest = tail;
+        Some(*value)
+    };
+
+    let time = f32::from_le_bytes(next()?);
+    let split_count = u32::from_le_bytes(next()?);
+    let splits = (0..split_count)
+        .map(|_| next().map(f32::from_le_bytes))
+        .collect::<Option<Vec<_>>>()?;
+
+    let frame_count = u32::from_le_bytes(next()?);
+    // the count comes from the file, a frame can't take up less than its 32 bytes
+    let mut frames = Vec::with_capacity((frame_count as usize).min(bytes.len() / 32));
+    for _ in 0..frame_count {
+        let mut values = [0.0; 8];
+        for value in values.iter_mut() {
+            *value = f32::from_le_bytes(next()?);
+        }
+        let [time, px, py, pz, qx, qy, qz, qw] = values;
+        frames.push(GhostFrame {
+            time,
+            position: Vec3::new(px, py, pz),
+            rotation: Quat::from_xyzw(qx, qy, qz, qw),
+        });
+    }
+
+    Some(Run {
+        time,
+        splits,
+        frames,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(time: f32, x: f32) -> GhostFrame {
+        GhostFrame {
+            time,
+            position: Vec3::new(x, 0.0, 0.0),
+            rotation: Quat::IDENTITY,
+        }
+    }
+
+    #[test]
+    fn ghost_path_stays_in_ghosts() {
+        assert_eq!(ghost_path("arena_1"), Path::new("ghosts/arena_1.ghost"));
+        assert_eq!(ghost_path("../../x"), Path::new("ghosts/______x.ghost"));
+        assert_eq!(
+            ghost_path("/etc/passwd"),
+            Path::new("ghosts/_etc_passwd.ghost")
+        );
+        assert_eq!(ghost_path(""), Path::new("ghosts/unnamed.ghost"));
+    }
+
+    #[test]
+    fn ghost_round_trip() {
+        let run = Run {
+            time: 12.5,
+            splits: vec![3.25, 7.0],
+            frames: vec![frame(0.0, 1.0), frame(0.5, 2.0)],
+        };
+        let path = std::env::temp_dir().join(format!("course-test-{}.ghost", std::process::id()));
+        save_ghost(&path, &run).unwrap();
+        let loaded = load_ghost(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(loaded.time, run.time);
+        assert_eq!(loaded.splits, run.splits);
+        assert_eq!(loaded.frames.len(), 2);
+        assert_eq!(loaded.frames[1].time, 0.5);
+        assert_eq!(loaded.frames[1].position, Vec3::new(2.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn sample_interpolates() {
+        let frames = [frame(0.0, 0.0), frame(1.0, 10.0)];
+        assert_eq!(sample(&frames, 0.25).unwrap().0.x, 2.5);
+        // past either end it holds the nearest frame
+        assert_eq!(sample(&frames, -1.0).unwrap().0.x, 0.0);
+        assert_eq!(sample(&frames, 5.0).unwrap().0.x, 10.0);
+        assert!(sample(&[], 0.5).is_none());
+    }
+
+    #[test]
+    fn splits_against_best() {
+        assert_eq!(split_line(0, 65.5, None), "CP1 1:05.500");
+        assert_eq!(
+            split_line(1, 9.0, Some(&[5.0, 10.0])),
+            "CP2 0:09.000 (-1.000)"
+        );
+        assert_eq!(split_line(2, 9.0, Some(&[5.0, 10.0])), "CP3 0:09.000");
+    }
+}
@@ -1,14 +1,156 @@
 use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts};
+use bevy_rapier3d::prelude::Velocity;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    editor::editor_closed,
+    player::{game_paused, Grounded, Player, ShotFired},
+    settings::{load_settings, save_settings},
+    share_code,
+};
 
 pub struct CrosshairPlugin;
 
 impl Plugin for CrosshairPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Startup, setup_crosshair);
+        app.insert_resource(load_settings::<CrosshairSettings>(SETTINGS_FILE))
+            .init_resource::<Spread>()
+            .add_systems(Startup, setup_crosshair)
+            .add_systems(
+                Update,
+                (
+                    crosshair_panel.run_if(game_paused).run_if(editor_closed),
+                    build_crosshair,
+                    update_spread,
+                    spread_crosshair,
+                )
+                    .chain(),
+            );
+    }
+}
+
+// where the player's crosshair is kept between sessions
+const SETTINGS_FILE: &str = "crosshair.ron";
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub enum CrosshairStyle {
+    Image,
+    Generated,
+}
+
+// all sizes are in pixels
+#[derive(Resource, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct CrosshairSettings {
+    pub style: CrosshairStyle,
+    // asset path used by CrosshairStyle::Image
+    pub image: String,
+    pub top: bool,
+    pub bottom: bool,
+    pub left: bool,
+    pub right: bool,
+    pub length: f32,
+    pub thickness: f32,
+    // distance from the center to where the lines start
+    pub gap: f32,
+    pub outline: bool,
+    pub outline_thickness: f32,
+    pub dot: bool,
+    pub color: [f32; 3],
+    pub opacity: f32,
+    // the gap opens up while moving, in the air and right after shooting
+    pub dynamic: bool,
+}
+
+impl Default for CrosshairSettings {
+    fn default() -> Self {
+        Self {
+            style: CrosshairStyle::Generated,
+            image: "crosshairtiny.png".into(),
+            top: true,
+            bottom: true,
+            left: true,
+            right: true,
+            length: 6.0,
+            thickness: 2.0,
+            gap: 3.0,
+            outline: true,
+            outline_thickness: 1.0,
+            dot: false,
+            color: [0.0, 1.0, 0.0],
+            opacity: 1.0,
+            dynamic: false,
+        }
+    }
+}
+
+// one filled rectangle of the crosshair, relative to the screen center
+#[derive(Component, Clone, Copy)]
+pub struct CrosshairRect {
+    pub rect: Rect,
+    pub color: Color,
+    // which way the rect moves when the gap opens up, zero for the center dot
+    pub push: Vec2,
+}
+
+impl CrosshairSettings {
+    // outline rects come first so they end up below
+    pub fn rects(&self) -> Vec<CrosshairRect> {
+        let half = self.thickness / 2.0;
+        let far = self.gap + self.length;
+        let mut shapes = Vec::new();
+
+        if self.length > 0.0 {
+            let arms = [
+                (
+                    self.top,
+                    Rect::new(-half, -far, half, -self.gap),
+                    Vec2::NEG_Y,
+                ),
+                (self.bottom, Rect::new(-half, self.gap, half, far), Vec2::Y),
+                (
+                    self.left,
+                    Rect::new(-far, -half, -self.gap, half),
+                    Vec2::NEG_X,
+                ),
+                (self.right, Rect::new(self.gap, -half, far, half), Vec2::X),
+            ];
+            shapes.extend(
+                arms.into_iter()
+                    .filter(|(on, _, _)| *on)
+                    .map(|(_, rect, push)| (rect, push)),
+            );
+        }
+        if self.dot {
+            shapes.push((Rect::new(-half, -half, half, half), Vec2::ZERO));
+        }
+
+        let color = Color::rgba(self.color[0], self.color[1], self.color[2], self.opacity);
+        let mut rects = Vec::new();
+        if self.outline {
+            let outline = Color::rgba(0.0, 0.0, 0.0, self.opacity);
+            let grow = Vec2::splat(self.outline_thickness);
+            rects.extend(shapes.iter().map(|(rect, push)| CrosshairRect {
+                rect: Rect::from_corners(rect.min - grow, rect.max + grow),
+                color: outline,
+                push: *push,
+            }));
+        }
+        rects.extend(
+            shapes
+                .into_iter()
+                .map(|(rect, push)| CrosshairRect { rect, color, push }),
+        );
+        rects
     }
 }
 
-fn setup_crosshair(mut commands: Commands, asset_server: ResMut<AssetServer>) {
+// zero sized node in the middle of the screen, the crosshair parts hang off it
+#[derive(Component)]
+struct CrosshairRoot;
+
+fn setup_crosshair(mut commands: Commands) {
     let thingy = NodeBundle {
         style: Style {
             width: Val::Vw(100.0),
@@ -20,15 +162,261 @@ fn setup_crosshair(mut commands: Commands, asset_server: ResMut<AssetServer>) {
         ..default()
     };
 
-    let poo = ImageBundle {
-        image: UiImage {
-            texture: asset_server.load("crosshairtiny.png"),
-            ..default()
-        },
-        ..default()
-    };
-
     commands.spawn(thingy).with_children(|parent| {
-        parent.spawn(poo);
+        parent.spawn((NodeBundle::default(), CrosshairRoot));
     });
 }
+
+fn build_crosshair(
+    mut commands: Commands,
+    settings: Res<CrosshairSettings>,
+    asset_server: Res<AssetServer>,
+    root_q: Query<Entity, With<CrosshairRoot>>,
+) {
+    if !settings.is_changed() {
+        return;
+    }
+
+    for root in root_q.iter() {
+        commands
+            .entity(root)
+            .despawn_descendants()
+            .with_children(|parent| match settings.style {
+                CrosshairStyle::Image => {
+                    let image = ImageBundle {
+                        image: UiImage {
+                            texture: asset_server.load(settings.image.clone()),
+                            ..default()
+                        },
+                        ..default()
+                    };
+                    // centers the image on the root like the rects below
+                    parent
+                        .spawn(NodeBundle {
+                            style: Style {
+                                position_type: PositionType::Absolute,
+                                width: Val::Px(0.0),
+                                height: Val::Px(0.0),
+                                align_items: AlignItems::Center,
+                                justify_content: JustifyContent::Center,
+                                ..default()
+                            },
+                            ..default()
+                        })
+                        .with_children(|parent| {
+                            parent.spawn(image);
+                        });
+                }
+                CrosshairStyle::Generated => {
+                    for part in settings.rects() {
+                        let rect = part.rect;
+                        parent.spawn((
+                            NodeBundle {
+                                style: Style {
+                                    position_type: PositionType::Absolute,
+                                    left: Val::Px(rect.min.x),
+                                    top: Val::Px(rect.min.y),
+                                    width: Val::Px(rect.width()),
+                                    height: Val::Px(rect.height()),
+                                    ..default()
+                                },
+                                background_color: part.color.into(),
+                                ..default()
+                            },
+                            part,
+                        ));
+                    }
+                }
+            });
+    }
+}
+
+fn crosshair_panel(
+    mut contexts: EguiContexts,
+    mut settings: ResMut<CrosshairSettings>,
+    mut code: Local<String>,
+    mut code_status: Local<String>,
+) {
+    // edit a copy so the crosshair is only rebuilt when something actually changed
+    let mut edited = settings.clone();
+
+    egui::Window::new("Crosshair").show(contexts.ctx_mut(), |ui| {
+        ui.horizontal(|ui| {
+            ui.radio_value(&mut edited.style, CrosshairStyle::Generated, "Generated");
+            ui.radio_value(&mut edited.style, CrosshairStyle::Image, "Image");
+        });
+
+        match edited.style {
+            CrosshairStyle::Image => {
+                ui.horizontal(|ui| {
+                    ui.label("Image");
+                    ui.text_edit_singleline(&mut edited.image);
+                });
+            }
+            CrosshairStyle::Generated => {
+                ui.horizontal(|ui| {
+                    ui.checkbox(&mut edited.top, "Top");
+                    ui.checkbox(&mut edited.bottom, "Bottom");
+                    ui.checkbox(&mut edited.left, "Left");
+                    ui.checkbox(&mut edited.right, "Right");
+                });
+                ui.add(egui::Slider::new(&mut edited.length, 0.0..=30.0).text("length"));
+                ui.add(egui::Slider::new(&mut edited.thickness, 0.5..=10.0).text("thickness"));
+                ui.add(egui::Slider::new(&mut edited.gap, -5.0..=20.0).text("gap"));
+                ui.horizontal(|ui| {
+                    ui.checkbox(&mut edited.outline, "Outline");
+                    ui.add_enabled(
+                        edited.outline,
+                        egui::Slider::new(&mut edited.outline_thickness, 0.5..=3.0),
+                    );
+                });
+                ui.checkbox(&mut edited.dot, "Center dot");
+                ui.horizontal(|ui| {
+                    ui.label("Color");
+                    ui.color_edit_button_rgb(&mut edited.color);
+                });
+                ui.add(egui::Slider::new(&mut edited.opacity, 0.0..=1.0).text("opacity"));
+                ui.checkbox(
+                    &mut edited.dynamic,
+                    "Dynamic (spreads when moving and shooting)",
+                );
+
+                preview(ui, &edited);
+            }
+        }
+
+        ui.separator();
+        ui.label("Share code (CS2 or Valorant)");
+        ui.text_edit_singleline(&mut *code);
+        ui.horizontal(|ui| {
+            if ui.button("Import").clicked() {
+                match share_code::import(&code) {
+                    Ok(imported) => {
+                        // codes don't have our own options, keep those
+                        edited = CrosshairSettings {
+                            image: edited.image.clone(),
+                            dynamic: edited.dynamic,
+                            ..imported
+                        };
+                        *code_status = "imported".into();
+                    }
+                    Err(err) => *code_status = err.to_string(),
+                }
+            }
+            let cs2 = ui.button("Copy as CS2").clicked();
+            let valorant = ui.button("Copy as Valorant").clicked();
+            let exported = if cs2 {
+                Some(share_code::to_cs2(&edited))
+            } else if valorant {
+                Some(share_code::to_valorant(&edited))
+            } else {
+                None
+            };
+            if let Some(exported) = exported {
+                ui.output_mut(|output| output.copied_text = exported.clone());
+                *code = exported;
+                *code_status = "copied to clipboard".into();
+            }
+        });
+        if !code_status.is_empty() {
+            ui.label(&*code_status);
+        }
+
+        ui.separator();
+        ui.horizontal(|ui| {
+            if ui.button("Save").clicked() {
+                save_settings(SETTINGS_FILE, &edited);
+            }
+            if ui.button("Reset").clicked() {
+                edited = CrosshairSettings::default();
+            }
+        });
+    });
+
+    if edited != *settings {
+        *settings = edited;
+    }
+}
+
+// the same rects drawn with egui, on grey so dark crosshairs still show up
+fn preview(ui: &mut egui::Ui, settings: &CrosshairSettings) {
+    let (area, _) = ui.allocate_exact_size(egui::vec2(96.0, 96.0), egui::Sense::hover());
+    let painter = ui.painter_at(area);
+    painter.rect_filled(area, 4.0, egui::Color32::from_gray(90));
+
+    let center = area.center().round();
+    for CrosshairRect { rect, color, .. } in settings.rects() {
+        let [r, g, b, a] = color.as_rgba_u8();
+        painter.rect_filled(
+            egui::Rect::from_min_max(
+                center + egui::vec2(rect.min.x, rect.min.y),
+                center + egui::vec2(rect.max.x, rect.max.y),
+            ),
+            0.0,
+            egui::Color32::from_rgba_unmultiplied(r, g, b, a),
+        );
+    }
+}
+
+// how far (pixels) the dynamic crosshair's lines are pushed out right now
+#[derive(Resource, Default)]
+struct Spread {
+    current: f32,
+    // from shooting, decays over time
+    recoil: f32,
+}
+
+// pixels per m/s of horizontal speed, and the most movement can add
+const MOVE_SPREAD: f32 = 0.8;
+const MAX_MOVE_SPREAD: f32 = 12.0;
+const AIR_SPREAD: f32 = 10.0;
+const SHOT_SPREAD: f32 = 5.0;
+const MAX_SHOT_SPREAD: f32 = 15.0;
+// per second, how quickly recoil spread goes away
+const RECOVERY: f32 = 6.0;
+// per second, how quickly the lines follow the spread
+const FOLLOW: f32 = 20.0;
+
+fn update_spread(
+    mut spread: ResMut<Spread>,
+    mut shots: EventReader<ShotFired>,
+    time: Res<Time>,
+    player_q: Query<(&Velocity, &Grounded), With<Player>>,
+) {
+    let dt = time.delta_seconds();
+
+    let shot_count = shots.read().count() as f32;
+    spread.recoil =
+        (spread.recoil * (-RECOVERY * dt).exp() + shot_count * SHOT_SPREAD).min(MAX_SHOT_SPREAD);
+
+    let mut target = spread.recoil;
+    for (velocity, grounded) in player_q.iter() {
+        target += (velocity.linvel.xz().length() * MOVE_SPREAD).min(MAX_MOVE_SPREAD);
+        if !grounded.0 {
+            target += AIR_SPREAD;
+        }
+    }
+
+    spread.current += (target - spread.current) * (1.0 - (-FOLLOW * dt).exp());
+}
+
+fn spread_crosshair(
+    settings: Res<CrosshairSettings>,
+    spread: Res<Spread>,
+    mut part_q: Query<(&mut Style, &CrosshairRect)>,
+) {
+    let offset = if settings.dynamic {
+        spread.current
+    } else {
+        0.0
+    };
+
+    for (mut style, part) in part_q.iter_mut() {
+        let min = part.rect.min + part.push * offset;
+        // only touch the style when it moved, changing it makes the ui relayout
+        if style.left != Val::Px(min.x) || style.top != Val::Px(min.y) {
+            style.left = Val::Px(min.x);
+            style.top = Val::Px(min.y);
+        }
+    }
+}
@@ -0,0 +1,454 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use bevy::{prelude::*, time::TimeUpdateStrategy};
+use bevy_egui::{egui, EguiContexts};
+
+use crate::{
+    editor::editor_closed,
+    input::{gather_input, InputFrame},
+    map::Map,
+    player::{game_paused, Player, Sensitivity},
+    rng::GameRng,
+    world::{CurrentMap, MapLoaded},
+};
+
+// Demos are every InputFrame of a session plus what it started from: the map, the target
+// seed and the sensitivity. Playing one back reloads the map, reseeds the targets and feeds
+// the frames (and their frame times) back in, so the player systems redo the same session.
+// Physics is deterministic enough on the same machine, across machines it can drift.
+//
+// F5 starts/stops recording, F6 plays the newest demo, F8 stops whatever is going on.
+// Those keys and the Demos window are DemoUiPlugin, DemoPlugin alone plays demos headless.
+pub struct DemoPlugin;
+
+impl Plugin for DemoPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Demo>()
+            .add_event::<DemoCommand>()
+            .add_systems(PreUpdate, run_demo.after(gather_input))
+            .add_systems(Update, handle_demo_commands);
+    }
+}
+
+pub struct DemoUiPlugin;
+
+impl Plugin for DemoUiPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (
+                demo_keys,
+                demo_panel.run_if(game_paused).run_if(editor_closed),
+            )
+                .chain()
+                .before(handle_demo_commands),
+        );
+    }
+}
+
+#[derive(Event)]
+pub enum DemoCommand {
+    Record,
+    Play(PathBuf),
+    Stop,
+}
+
+#[derive(Default, PartialEq)]
+enum DemoMode {
+    #[default]
+    Idle,
+    Recording,
+    Playing,
+}
+
+#[derive(Resource, Default)]
+pub struct Demo {
+    mode: DemoMode,
+    // the map is being rebuilt, frames start once it's done
+    waiting: bool,
+    seed: u64,
+    sensitivity: f32,
+    map: Map,
+    frames: Vec<InputFrame>,
+    cursor: usize,
+    // player's own sensitivity, put back after playback
+    saved_sensitivity: Option<f32>,
+}
+
+pub fn playing_demo(demo: Res<Demo>) -> bool {
+    demo.mode == DemoMode::Playing
+}
+
+const DEMO_DIR: &str = "demos";
+const VERSION: u8 = 1;
+// seconds, longer frames aren't recorded (bevy caps a frame's time at a quarter second) so
+// a demo that has them is broken
+const MAX_FRAME_DT: f32 = 1.0;
+
+fn demo_keys(keys: Res<Input<KeyCode>>, demo: Res<Demo>, mut commands: EventWriter<DemoCommand>) {
+    if keys.just_pressed(KeyCode::F5) {
+        if demo.mode == DemoMode::Recording {
+            commands.send(DemoCommand::Stop);
+        } else {
+            commands.send(DemoCommand::Record);
+        }
+    }
+    if keys.just_pressed(KeyCode::F6) {
+        match list_demos().pop() {
+            Some(path) => commands.send(DemoCommand::Play(path)),
+            None => warn!("no demos in {DEMO_DIR}/"),
+        }
+    }
+    if keys.just_pressed(KeyCode::F8) {
+        commands.send(DemoCommand::Stop);
+    }
+}
+
+fn demo_panel(mut contexts: EguiContexts, demo: Res<Demo>, mut commands: EventWriter<DemoCommand>) {
+    egui::Window::new("Demos").show(contexts.ctx_mut(), |ui| {
+        match demo.mode {
+            DemoMode::Idle => {
+                if ui.button("Record (F5)").clicked() {
+                    commands.send(DemoCommand::Record);
+                }
+            }
+            DemoMode::Recording => {
+                ui.label(format!("Recording, {} frames", demo.frames.len()));
+                if ui.button("Stop (F8)").clicked() {
+                    commands.send(DemoCommand::Stop);
+                }
+            }
+            DemoMode::Playing => {
+                ui.label(format!("Playing {}/{}", demo.cursor, demo.frames.len()));
+                if ui.button("Stop (F8)").clicked() {
+                    commands.send(DemoCommand::Stop);
+                }
+            }
+        }
+
+        ui.separator();
+        for path in list_demos().into_iter().rev() {
+            ui.horizontal(|ui| {
+                ui.label(path.file_name().unwrap_or_default().to_string_lossy());
+                if ui.button("Play").clicked() {
+                    commands.send(DemoCommand::Play(path));
+                }
+            });
+        }
+    });
+}
+
+#[allow(clippy::too_many_arguments)]
+fn handle_demo_commands(
+    mut events: EventReader<DemoCommand>,
+    mut demo: ResMut<Demo>,
+    mut rng: ResMut<GameRng>,
+    mut maps: ResMut<Assets<Map>>,
+    mut current: ResMut<CurrentMap>,
+    mut time_strategy: ResMut<TimeUpdateStrategy>,
+    mut player_q: Query<&mut Sensitivity, With<Player>>,
+) {
+    for event in events.read() {
+        stop(&mut demo, &mut time_strategy, &mut player_q);
+
+        match event {
+            DemoCommand::Record => {
+                let Some(map) = maps.get(current.0.id()) else {
+                    warn!("can't record a demo before the map has loaded");
+                    continue;
+                };
+                let Ok(sensitivity) = player_q.get_single() else {
+                    continue;
+                };
+
+                *demo = Demo {
+                    mode: DemoMode::Recording,
+                    waiting: true,
+                    seed: rng.session_seed(),
+                    sensitivity: sensitivity.0,
+                    map: map.clone(),
+                    ..default()
+                };
+            }
+            DemoCommand::Play(path) => {
+                let loaded = match load_demo(path) {
+                    Ok(loaded) => loaded,
+                    Err(err) => {
+                        warn!("could not load demo {}: {err}", path.display());
+                        continue;
+                    }
+                };
+                let Ok(mut sensitivity) = player_q.get_single_mut() else {
+                    continue;
+                };
+
+                demo.saved_sensitivity = Some(sensitivity.0);
+                sensitivity.0 = loaded.sensitivity;
+                *demo = Demo {
+                    mode: DemoMode::Playing,
+                    waiting: true,
+                    saved_sensitivity: demo.saved_sensitivity,
+                    ..loaded
+                };
+                // the first frame has to run with the recorded frame time too
+                if let Some(first) = demo.frames.first() {
+                    *time_strategy =
+                        TimeUpdateStrategy::ManualDuration(Duration::from_secs_f32(first.dt));
+                }
+            }
+            DemoCommand::Stop => continue,
+        }
+
+        // a fresh copy of the map makes world.rs rebuild it, which restarts the rng and
+        // respawns the player and the targets
+        rng.set_session_seed(demo.seed);
+        current.0 = maps.add(demo.map.clone());
+    }
+}
+
+// ends recording (saving the demo) or playback
+fn stop(
+    demo: &mut Demo,
+    time_strategy: &mut TimeUpdateStrategy,
+    player_q: &mut Query<&mut Sensitivity, With<Player>>,
+) {
+    match demo.mode {
+        DemoMode::Idle => return,
+        DemoMode::Recording => {
+            if !demo.waiting {
+                let seconds = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .unwrap_or_default()
+                    .as_secs();
+                let path = Path::new(DEMO_DIR).join(format!("{seconds}.demo"));
+                match save_demo(&path, demo) {
+                    Ok(()) => info!("saved demo to {}", path.display()),
+                    Err(err) => warn!("could not save demo: {err}"),
+                }
+            }
+        }
+        DemoMode::Playing => {
+            *time_strategy = TimeUpdateStrategy::Automatic;
+            if let (Some(saved), Ok(mut sensitivity)) =
+                (demo.saved_sensitivity, player_q.get_single_mut())
+            {
+                sensitivity.0 = saved;
+            }
+        }
+    }
+
+    *demo = Demo::default();
+}
+
+// runs right after the real input is gathered: records it, or replaces it with the demo's
+pub fn run_demo(
+    mut demo: ResMut<Demo>,
+    mut input: ResMut<InputFrame>,
+    mut loaded: EventReader<MapLoaded>,
+    mut time_strategy: ResMut<TimeUpdateStrategy>,
+    mut player_q: Query<&mut Sensitivity, With<Player>>,
+) {
+    if demo.mode == DemoMode::Idle {
+        loaded.clear();
+        return;
+    }
+
+    if demo.waiting {
+        if loaded.read().next().is_none() {
+            // nobody moves while the map is rebuilt
+            *input = InputFrame {
+                dt: input.dt,
+                ..default()
+            };
+            return;
+        }
+        demo.waiting = false;
+    }
+
+    match demo.mode {
+        DemoMode::Recording => demo.frames.push(*input),
+        DemoMode::Playing => {
+            let Some(frame) = demo.frames.get(demo.cursor).copied() else {
+                info!("demo finished");
+                stop(&mut demo, &mut time_strategy, &mut player_q);
+                return;
+            };
+            *input = frame;
+            demo.cursor += 1;
+
+            // the time for the next frame gets updated before this system runs again
+            if let Some(next) = demo.frames.get(demo.cursor) {
+                *time_strategy =
+                    TimeUpdateStrategy::ManualDuration(Duration::from_secs_f32(next.dt));
+            }
+        }
+        DemoMode::Idle => {}
+    }
+}
+
+fn list_demos() -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(DEMO_DIR) else {
+        return Vec::new();
+    };
+    let mut paths: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "demo"))
+        .collect();
+    // names are unix timestamps, so this is oldest first
+    paths.sort();
+    paths
+}
+
+// little endian: magic, version, seed, sensitivity, the map as ron, then
+// held + pressed + look + dt per frame
+fn save_demo(path: &Path, demo: &Demo) -> io::Result<()> {
+    let bytes = demo_bytes(demo).map_err(|err| io::Error::other(err.to_string()))?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(path, bytes)
+}
+
+fn demo_bytes(demo: &Demo) -> ron::Result<Vec<u8>> {
+    let map = ron::to_string(&demo.map)?;
+
+    let mut bytes = Vec::with_capacity(25 + map.len() + demo.frames.len() * 16);
+    bytes.extend_from_slice(b"DEMO");
+    bytes.push(VERSION);
+    bytes.extend_from_slice(&demo.seed.to_le_bytes());
+    bytes.extend_from_slice(&demo.sensitivity.to_le_bytes());
+    bytes.extend_from_slice(&(map.len() as u32).to_le_bytes());
+    bytes.extend_from_slice(map.as_bytes());
+    bytes.extend_from_slice(&(demo.frames.len() as u32).to_le_bytes());
+    for frame in demo.frames.iter() {
+        bytes.extend_from_slice(&frame.held.to_le_bytes());
+        bytes.extend_from_slice(&frame.pressed.to_le_bytes());
+        bytes.extend_from_slice(&frame.look.x.to_le_bytes());
+        bytes.extend_from_slice(&frame.look.y.to_le_bytes());
+        bytes.extend_from_slice(&frame.dt.to_le_bytes());
+    }
+    Ok(bytes)
+}
+
+fn load_demo(path: &Path) -> io::Result<Demo> {
+    let bytes = fs::read(path)?;
+    parse_demo(&bytes).ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "not a demo"))
+}
+
+// demos get shared, so anything that would upset playback is rejected here
+fn parse_demo(bytes: &[u8]) -> Option<Demo> {
+    let mut rest = bytes.strip_prefix(b"DEMO")?;
+    let mut take = |len: usize| {
+        let all: &[u8] = rest;
+        if all.len() < len {
+            return None;
+        }
+        let (value, tail) = all.split_at(len);
+        rest = tail;
+        Some(value)
+    };
+
+    if take(1)?[0] != VERSION {
+        return None;
+    }
+    let seed = u64::from_le_bytes(take(8)?.try_into().ok()?);
+    let sensitivity = f32::from_le_bytes(take(4)?.try_into().ok()?);
+    if !sensitivity.is_finite() {
+        return None;
+    }
+    let map_len = u32::from_le_bytes(take(4)?.try_into().ok()?);
+    let map = ron::from_str(std::str::from_utf8(take(map_len as usize)?).ok()?).ok()?;
+
+    let frame_count = u32::from_le_bytes(take(4)?.try_into().ok()?);
+    // the count comes from the file, a frame can't take up less than its 16 bytes
+    let mut frames = Vec::with_capacity((frame_count as usize).min(bytes.len() / 16));
+    for _ in 0..frame_count {
+        let held = u16::from_le_bytes(take(2)?.try_into().ok()?);
+        let pressed = u16::from_le_bytes(take(2)?.try_into().ok()?);
+        let x = f32::from_le_bytes(take(4)?.try_into().ok()?);
+        let y = f32::from_le_bytes(take(4)?.try_into().ok()?);
+        let dt = f32::from_le_bytes(take(4)?.try_into().ok()?);
+        // the frame times become Durations, which panic on NaN or negative ones
+        if !x.is_finite() || !y.is_finite() || !(0.0..=MAX_FRAME_DT).contains(&dt) {
+            return None;
+        }
+        frames.push(InputFrame {
+            held,
+            pressed,
+            look: Vec2::new(x, y),
+            dt,
+        });
+    }
+
+    if !rest.is_empty() {
+        return None;
+    }
+    Some(Demo {
+        seed,
+        sensitivity,
+        map,
+        frames,
+        ..default()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn demo() -> Demo {
+        Demo {
+            seed: 42,
+            sensitivity: 0.8,
+            map: Map::default(),
+            frames: (0..10)
+                .map(|i| InputFrame {
+                    held: i,
+                    pressed: i % 2,
+                    look: Vec2::new(i as f32, -0.5),
+                    dt: 1.0 / 60.0,
+                })
+                .collect(),
+            ..default()
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let demo = demo();
+        let parsed = parse_demo(&demo_bytes(&demo).unwrap()).unwrap();
+        assert_eq!(parsed.seed, demo.seed);
+        assert_eq!(parsed.sensitivity, demo.sensitivity);
+        assert_eq!(parsed.map, demo.map);
+        assert_eq!(parsed.frames, demo.frames);
+    }
+
+    #[test]
+    fn truncated() {
+        let bytes = demo_bytes(&demo()).unwrap();
+        for len in 0..bytes.len() {
+            assert!(parse_demo(&bytes[..len]).is_none(), "{len} bytes");
+        }
+    }
+
+    #[test]
+    fn bad_frame_times() {
+        for dt in [f32::NAN, f32::INFINITY, -1.0 / 60.0, MAX_FRAME_DT * 2.0] {
+            let mut demo = demo();
+            demo.frames[3].dt = dt;
+            assert!(parse_demo(&demo_bytes(&demo).unwrap()).is_none(), "dt {dt}");
+        }
+    }
+
+    #[test]
+    fn corrupt() {
+        let mut bytes = demo_bytes(&demo()).unwrap();
+        bytes[4] = VERSION + 1;
+        assert!(parse_demo(&bytes).is_none());
+        assert!(parse_demo(b"not a demo at all").is_none());
+    }
+}
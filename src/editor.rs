@@ -0,0 +1,1165 @@
+use bevy::{
+    input::mouse::{MouseMotion, MouseWheel},
+    prelude::*,
+    window::{CursorGrabMode, PrimaryWindow},
+};
+use bevy_egui::{egui, EguiContexts};
+use bevy_rapier3d::prelude::*;
+
+use crate::{
+    console::console_open,
+    map::{
+        Geometry, LightKind, Map, MapLight, MapMaterial, PlayerSpawn, Shape, TargetVolume, Trigger,
+        TriggerKind,
+    },
+    world::{CurrentMap, EditInPlace, MapItem},
+};
+
+// Edits go into a draft copy of the loaded Map first. While something is being dragged only
+// the moved entities follow it, and once the drag is over the draft is written into the Map
+// asset, which makes world.rs rebuild the level in place (see EditInPlace). Spawn points and
+// target volumes only take effect the next time the map loads, saving it counts.
+pub struct EditorPlugin;
+
+impl Plugin for EditorPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<EditorState>()
+            .add_event::<ToggleEditor>()
+            .add_systems(
+                Update,
+                (
+                    toggle_editor,
+                    (
+                        // the console has the keyboard while it's open
+                        fly_camera.run_if(not(console_open)),
+                        pick,
+                        transform_tool.run_if(not(console_open)),
+                        inspector,
+                        preview_draft,
+                        commit_draft,
+                        draw_gizmos,
+                    )
+                        .chain()
+                        .run_if(editor_open),
+                ),
+            );
+    }
+}
+
+// sent by the pause menu to go in and out of the editor
+#[derive(Event)]
+pub struct ToggleEditor;
+
+#[derive(Resource)]
+pub struct EditorState {
+    open: bool,
+    selected: Option<Selection>,
+    tool: Option<ActiveTool>,
+    // edits that haven't been written into the Map asset yet
+    draft: Option<Map>,
+    fly_speed: f32,
+    save_path: String,
+    status: String,
+    // cameras switched off while the editor is open
+    hidden: Vec<Entity>,
+}
+
+impl Default for EditorState {
+    fn default() -> Self {
+        Self {
+            open: false,
+            selected: None,
+            tool: None,
+            draft: None,
+            fly_speed: 10.0,
+            save_path: String::new(),
+            status: String::new(),
+            hidden: Vec::new(),
+        }
+    }
+}
+
+// no EditorPlugin (headless) counts as closed
+pub fn editor_open(state: Option<Res<EditorState>>) -> bool {
+    state.is_some_and(|state| state.open)
+}
+
+pub fn editor_closed(state: Option<Res<EditorState>>) -> bool {
+    !editor_open(state)
+}
+
+// the map as it looks in the editor, draft included
+fn working_map<'a>(
+    state: &'a EditorState,
+    maps: &'a Assets<Map>,
+    current: &CurrentMap,
+) -> Option<&'a Map> {
+    state.draft.as_ref().or_else(|| maps.get(current.0.id()))
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Selection {
+    Geometry(usize),
+    Light(usize),
+    Trigger(usize),
+    PlayerSpawn(usize),
+    TargetVolume(usize),
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum ToolMode {
+    Grab,
+    Rotate,
+    Scale,
+}
+
+struct ActiveTool {
+    mode: ToolMode,
+    axis: Option<usize>,
+    // restored if the tool is cancelled
+    original: Map,
+}
+
+#[derive(Component)]
+struct EditorCamera;
+
+const AXES: [Vec3; 3] = [Vec3::X, Vec3::Y, Vec3::Z];
+const AXIS_COLORS: [Color; 3] = [Color::RED, Color::GREEN, Color::BLUE];
+const MARKER_RADIUS: f32 = 0.5;
+
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+fn toggle_editor(
+    mut commands: Commands,
+    mut events: EventReader<ToggleEditor>,
+    mut state: ResMut<EditorState>,
+    mut game_cam_q: Query<
+        (Entity, &mut Camera, &GlobalTransform),
+        (With<Camera3d>, Without<EditorCamera>),
+    >,
+    editor_cam_q: Query<Entity, With<EditorCamera>>,
+    current: Option<Res<CurrentMap>>,
+    mut maps: ResMut<Assets<Map>>,
+    mut edit_in_place: ResMut<EditInPlace>,
+    asset_server: Res<AssetServer>,
+) {
+    if events.read().count().is_multiple_of(2) {
+        return;
+    }
+
+    state.open = !state.open;
+    state.selected = None;
+    state.tool = None;
+
+    // leaving halfway through a drag keeps it
+    if let (Some(draft), Some(current)) = (state.draft.take(), current.as_ref()) {
+        write_draft(draft, &mut maps, current, &mut edit_in_place);
+    }
+
+    if state.open {
+        // start the editor camera where the game (player or spectator) was looking from
+        let mut start = Transform::default();
+        for (entity, mut camera, global_transform) in game_cam_q.iter_mut() {
+            if !camera.is_active {
+                continue;
+            }
+            camera.is_active = false;
+            state.hidden.push(entity);
+            start = global_transform.compute_transform();
+        }
+
+        commands.spawn((
+            Camera3dBundle {
+                transform: start,
+                camera: Camera {
+                    hdr: true,
+                    ..default()
+                },
+                ..default()
+            },
+            EditorCamera,
+        ));
+
+        let Some(current) = current else {
+            return;
+        };
+        state.save_path = match asset_server.get_path(current.0.id()) {
+            Some(path) => format!("assets/{}", path.path().display()),
+            // generated maps have no file yet
+            None => match maps.get(current.0.id()) {
+                Some(map) if !map.name.is_empty() => format!("assets/maps/{}.map.ron", map.name),
+                _ => "assets/maps/untitled.map.ron".to_string(),
+            },
+        };
+    } else {
+        for entity in editor_cam_q.iter() {
+            commands.entity(entity).despawn_recursive();
+        }
+        for entity in std::mem::take(&mut state.hidden) {
+            if let Ok((_, mut camera, _)) = game_cam_q.get_mut(entity) {
+                camera.is_active = true;
+            }
+        }
+    }
+}
+
+// hold right mouse to look around, WASD to move, Q/E for down/up, scroll for speed
+#[allow(clippy::too_many_arguments)]
+fn fly_camera(
+    keys: Res<Input<KeyCode>>,
+    mouse_buttons: Res<Input<MouseButton>>,
+    mut motion_evr: EventReader<MouseMotion>,
+    mut wheel_evr: EventReader<MouseWheel>,
+    time: Res<Time>,
+    mut state: ResMut<EditorState>,
+    mut cam_q: Query<&mut Transform, With<EditorCamera>>,
+    mut q_windows: Query<&mut Window, With<PrimaryWindow>>,
+) {
+    let looking = mouse_buttons.pressed(MouseButton::Right) && state.tool.is_none();
+
+    if let Ok(mut primary_window) = q_windows.get_single_mut() {
+        primary_window.cursor.grab_mode = if looking {
+            CursorGrabMode::Locked
+        } else {
+            CursorGrabMode::None
+        };
+        primary_window.cursor.visible = !looking;
+    }
+
+    for ev in wheel_evr.read() {
+        state.fly_speed = (state.fly_speed * (1.0 + ev.y * 0.1)).clamp(1.0, 200.0);
+    }
+
+    let Ok(mut cam) = cam_q.get_single_mut() else {
+        return;
+    };
+
+    if !looking {
+        motion_evr.clear();
+        return;
+    }
+
+    let (mut yaw, mut pitch, _) = cam.rotation.to_euler(EulerRot::YXZ);
+    for ev in motion_evr.read() {
+        pitch -= (ev.delta.y * 0.1).to_radians();
+        yaw -= (ev.delta.x * 0.1).to_radians();
+    }
+    pitch = pitch.clamp(-1.54, 1.54);
+    cam.rotation = Quat::from_axis_angle(Vec3::Y, yaw) * Quat::from_axis_angle(Vec3::X, pitch);
+
+    let mut direction = Vec3::ZERO;
+    if keys.pressed(KeyCode::W) {
+        direction += cam.forward();
+    }
+    if keys.pressed(KeyCode::S) {
+        direction += cam.back();
+    }
+    if keys.pressed(KeyCode::A) {
+        direction += cam.left();
+    }
+    if keys.pressed(KeyCode::D) {
+        direction += cam.right();
+    }
+    if keys.pressed(KeyCode::E) {
+        direction += Vec3::Y;
+    }
+    if keys.pressed(KeyCode::Q) {
+        direction -= Vec3::Y;
+    }
+
+    let boost = if keys.pressed(KeyCode::ShiftLeft) {
+        3.0
+    } else {
+        1.0
+    };
+    cam.translation +=
+        direction.normalize_or_zero() * state.fly_speed * boost * time.delta_seconds();
+}
+
+#[allow(clippy::too_many_arguments)]
+fn pick(
+    mut state: ResMut<EditorState>,
+    mouse_buttons: Res<Input<MouseButton>>,
+    mut contexts: EguiContexts,
+    q_windows: Query<&Window, With<PrimaryWindow>>,
+    cam_q: Query<(&Camera, &GlobalTransform), With<EditorCamera>>,
+    rapier_context: Res<RapierContext>,
+    item_q: Query<&MapItem>,
+    current: Res<CurrentMap>,
+    maps: Res<Assets<Map>>,
+) {
+    if !mouse_buttons.just_pressed(MouseButton::Left)
+        || state.tool.is_some()
+        || contexts.ctx_mut().wants_pointer_input()
+    {
+        return;
+    }
+
+    let Some(cursor) = q_windows
+        .get_single()
+        .ok()
+        .and_then(|w| w.cursor_position())
+    else {
+        return;
+    };
+    let Ok((camera, cam_transform)) = cam_q.get_single() else {
+        return;
+    };
+    let Some(ray) = camera.viewport_to_world(cam_transform, cursor) else {
+        return;
+    };
+
+    let mut best: Option<(f32, Selection)> = None;
+
+    if let Some((entity, toi)) = rapier_context.cast_ray(
+        ray.origin,
+        ray.direction,
+        1000.0,
+        true,
+        QueryFilter::only_fixed(),
+    ) {
+        if let Ok(item) = item_q.get(entity) {
+            let selection = match *item {
+                MapItem::Geometry(index) => Selection::Geometry(index),
+                MapItem::Light(index) => Selection::Light(index),
+                MapItem::Trigger(index) => Selection::Trigger(index),
+            };
+            best = Some((toi, selection));
+        }
+    }
+
+    // things without colliders are picked by their marker sphere
+    if let Some(map) = working_map(&state, &maps, &current) {
+        for (selection, point) in markers(map) {
+            let toi = (point - ray.origin).dot(ray.direction);
+            let closer = best.is_none_or(|(best_toi, _)| toi < best_toi);
+            if toi > 0.0 && closer && ray.get_point(toi).distance(point) < MARKER_RADIUS {
+                best = Some((toi, selection));
+            }
+        }
+    }
+
+    state.selected = best.map(|(_, selection)| selection);
+}
+
+// G/R/S to grab, rotate or scale the selection with the mouse, X/Y/Z to lock an axis,
+// left click or enter to confirm, right click or escape to cancel
+#[allow(clippy::too_many_arguments)]
+fn transform_tool(
+    mut state: ResMut<EditorState>,
+    keys: Res<Input<KeyCode>>,
+    mouse_buttons: Res<Input<MouseButton>>,
+    mut motion_evr: EventReader<MouseMotion>,
+    mut contexts: EguiContexts,
+    cam_q: Query<&Transform, With<EditorCamera>>,
+    current: Res<CurrentMap>,
+    maps: Res<Assets<Map>>,
+) {
+    let delta: Vec2 = motion_evr.read().map(|ev| ev.delta).sum();
+
+    if contexts.ctx_mut().wants_keyboard_input() {
+        return;
+    }
+    let Some(map) = working_map(&state, &maps, &current).cloned() else {
+        return;
+    };
+    let Some(selection) = state.selected else {
+        return;
+    };
+    let Ok(cam) = cam_q.get_single() else {
+        return;
+    };
+
+    let mut edited = map.clone();
+
+    if state.tool.is_none() {
+        if mouse_buttons.pressed(MouseButton::Right) {
+            return;
+        }
+
+        let mode = if keys.just_pressed(KeyCode::G) {
+            Some(ToolMode::Grab)
+        } else if keys.just_pressed(KeyCode::R) {
+            Some(ToolMode::Rotate)
+        } else if keys.just_pressed(KeyCode::S) {
+            Some(ToolMode::Scale)
+        } else {
+            None
+        };
+
+        if let Some(mode) = mode {
+            state.tool = Some(ActiveTool {
+                mode,
+                axis: None,
+                original: map.clone(),
+            });
+        } else if keys.just_pressed(KeyCode::Delete) {
+            remove(&mut edited, selection);
+            state.selected = None;
+        } else if keys.pressed(KeyCode::ControlLeft) && keys.just_pressed(KeyCode::D) {
+            state.selected = duplicate(&mut edited, selection);
+        }
+    } else if keys.just_pressed(KeyCode::Escape) || mouse_buttons.just_pressed(MouseButton::Right) {
+        if let Some(tool) = state.tool.take() {
+            edited = tool.original;
+        }
+    } else if keys.just_pressed(KeyCode::Return) || mouse_buttons.just_pressed(MouseButton::Left) {
+        state.tool = None;
+    } else if let Some(tool) = state.tool.as_mut() {
+        for (index, key) in [KeyCode::X, KeyCode::Y, KeyCode::Z].into_iter().enumerate() {
+            if keys.just_pressed(key) {
+                tool.axis = Some(index);
+            }
+        }
+
+        let center = center(&edited, selection).unwrap_or_default();
+
+        match tool.mode {
+            ToolMode::Grab => {
+                // move further per pixel the further away the thing is
+                let speed = 0.002 * cam.translation.distance(center).max(1.0);
+                let plane_move = (cam.right() * delta.x - cam.up() * delta.y) * speed;
+                let offset = match tool.axis {
+                    Some(index) if AXES[index].dot(cam.forward()).abs() > 0.9 => {
+                        AXES[index] * -delta.y * speed
+                    }
+                    Some(index) => AXES[index] * plane_move.dot(AXES[index]),
+                    None => plane_move,
+                };
+                translate(&mut edited, selection, offset);
+            }
+            ToolMode::Rotate => {
+                rotate(
+                    &mut edited,
+                    selection,
+                    tool.axis.unwrap_or(1),
+                    delta.x * 0.5,
+                );
+            }
+            ToolMode::Scale => {
+                scale(&mut edited, selection, tool.axis, 1.0 + delta.x * 0.005);
+            }
+        }
+    }
+
+    if edited != map {
+        state.draft = Some(edited);
+    }
+}
+
+fn inspector(
+    mut contexts: EguiContexts,
+    mut state: ResMut<EditorState>,
+    current: Res<CurrentMap>,
+    maps: Res<Assets<Map>>,
+    cam_q: Query<&Transform, With<EditorCamera>>,
+    mut toggle: EventWriter<ToggleEditor>,
+) {
+    let Some(map) = working_map(&state, &maps, &current).cloned() else {
+        return;
+    };
+    let mut edited = map.clone();
+
+    // new things get dropped a bit in front of the camera
+    let drop_point = cam_q
+        .get_single()
+        .map(|cam| (cam.translation + cam.forward() * 8.0).to_array())
+        .unwrap_or_default();
+
+    egui::Window::new("Level editor").show(contexts.ctx_mut(), |ui| {
+        ui.horizontal(|ui| {
+            ui.label("File");
+            ui.text_edit_singleline(&mut state.save_path);
+        });
+        ui.horizontal(|ui| {
+            if ui.button("Save").clicked() {
+                state.status = match save_map(&edited, &state.save_path) {
+                    Ok(()) => format!("saved {}", state.save_path),
+                    Err(err) => format!("could not save: {err}"),
+                };
+            }
+            if ui.button("Exit editor").clicked() {
+                toggle.send(ToggleEditor);
+            }
+        });
+        if !state.status.is_empty() {
+            ui.label(&state.status);
+        }
+        ui.label("Map name");
+        ui.text_edit_singleline(&mut edited.name);
+        ui.add(egui::Slider::new(&mut state.fly_speed, 1.0..=200.0).text("fly speed"));
+        ui.label("RMB + WASD/QE fly, click select, G/R/S grab/rotate/scale, X/Y/Z axis, Del delete, Ctrl+D duplicate");
+
+        ui.separator();
+        ui.horizontal_wrapped(|ui| {
+            let material = edited.materials.keys().next().cloned().unwrap_or_default();
+            let add_geometry = |edited: &mut Map, shape: Shape| {
+                edited.geometry.push(Geometry {
+                    shape,
+                    position: drop_point,
+                    rotation: [0.0; 3],
+                    material: material.clone(),
+                });
+                Some(Selection::Geometry(edited.geometry.len() - 1))
+            };
+            if ui.button("+ Cube").clicked() {
+                state.selected = add_geometry(&mut edited, Shape::Cuboid { size: [2.0; 3] });
+            }
+            if ui.button("+ Ramp").clicked() {
+                state.selected = add_geometry(
+                    &mut edited,
+                    Shape::Ramp {
+                        size: [4.0, 2.0, 6.0],
+                    },
+                );
+            }
+            if ui.button("+ Cylinder").clicked() {
+                state.selected = add_geometry(
+                    &mut edited,
+                    Shape::Cylinder {
+                        radius: 1.0,
+                        height: 4.0,
+                    },
+                );
+            }
+            if ui.button("+ Light").clicked() {
+                edited.lights.push(MapLight {
+                    kind: LightKind::Point,
+                    position: drop_point,
+                    rotation: [0.0; 3],
+                    color: [1.0; 3],
+                    intensity: 2000.0,
+                    range: 20.0,
+                    shadows: false,
+                });
+                state.selected = Some(Selection::Light(edited.lights.len() - 1));
+            }
+            let add_trigger = |edited: &mut Map, kind: TriggerKind| {
+                edited.triggers.push(Trigger {
+                    kind,
+                    position: drop_point,
+                    rotation: [0.0; 3],
+                    size: [3.0, 0.5, 3.0],
+                });
+                Some(Selection::Trigger(edited.triggers.len() - 1))
+            };
+            if ui.button("+ Jump pad").clicked() {
+                state.selected = add_trigger(
+                    &mut edited,
+                    TriggerKind::JumpPad {
+                        velocity: [0.0, 15.0, 0.0],
+                    },
+                );
+            }
+            if ui.button("+ Speed pad").clicked() {
+                state.selected = add_trigger(&mut edited, TriggerKind::SpeedPad { speed: 15.0 });
+            }
+            if ui.button("+ Teleporter").clicked() {
+                state.selected = add_trigger(
+                    &mut edited,
+                    TriggerKind::Teleporter {
+                        destination: [0.0, 1.0, 0.0],
+                        yaw: None,
+                        keep_velocity: false,
+                    },
+                );
+            }
+            if ui.button("+ Course start").clicked() {
+                state.selected = add_trigger(&mut edited, TriggerKind::CourseStart);
+            }
+            if ui.button("+ Checkpoint").clicked() {
+                let order = edited
+                    .triggers
+                    .iter()
+                    .filter(|trigger| matches!(trigger.kind, TriggerKind::Checkpoint { .. }))
+                    .count() as u32;
+                state.selected = add_trigger(&mut edited, TriggerKind::Checkpoint { order });
+            }
+            if ui.button("+ Course finish").clicked() {
+                state.selected = add_trigger(&mut edited, TriggerKind::CourseFinish);
+            }
+            if ui.button("+ Player spawn").clicked() {
+                edited.player_spawns.push(PlayerSpawn {
+                    position: drop_point,
+                    yaw: 0.0,
+                });
+                state.selected = Some(Selection::PlayerSpawn(edited.player_spawns.len() - 1));
+            }
+            if ui.button("+ Target spawner").clicked() {
+                let center = Vec3::from(drop_point);
+                edited.target_volumes.push(TargetVolume {
+                    min: (center - Vec3::new(0.0, 4.0, 4.0)).into(),
+                    max: (center + Vec3::new(0.0, 4.0, 4.0)).into(),
+                    count: 2,
+                    health: 1,
+                });
+                state.selected = Some(Selection::TargetVolume(edited.target_volumes.len() - 1));
+            }
+        });
+
+        ui.separator();
+        egui::ScrollArea::vertical()
+            .id_source("outline")
+            .max_height(160.0)
+            .show(ui, |ui| {
+                let items = (0..edited.geometry.len())
+                    .map(Selection::Geometry)
+                    .chain((0..edited.lights.len()).map(Selection::Light))
+                    .chain((0..edited.triggers.len()).map(Selection::Trigger))
+                    .chain((0..edited.player_spawns.len()).map(Selection::PlayerSpawn))
+                    .chain((0..edited.target_volumes.len()).map(Selection::TargetVolume));
+                for item in items {
+                    let selected = state.selected == Some(item);
+                    if ui
+                        .selectable_label(selected, describe(&edited, item))
+                        .clicked()
+                    {
+                        state.selected = Some(item);
+                    }
+                }
+            });
+
+        ui.separator();
+        if let Some(selection) = state.selected {
+            selection_ui(ui, &mut edited, selection);
+        }
+
+        ui.collapsing("Materials", |ui| {
+            for (name, material) in edited.materials.iter_mut() {
+                ui.horizontal(|ui| {
+                    ui.label(name);
+                    ui.color_edit_button_rgba_unmultiplied(&mut material.color);
+                    ui.add(
+                        egui::DragValue::new(&mut material.roughness)
+                            .speed(0.01)
+                            .clamp_range(0.0..=1.0)
+                            .prefix("rough "),
+                    );
+                    ui.add(
+                        egui::DragValue::new(&mut material.metallic)
+                            .speed(0.01)
+                            .clamp_range(0.0..=1.0)
+                            .prefix("metal "),
+                    );
+                });
+            }
+            if ui.button("+ Material").clicked() {
+                let name = format!("material{}", edited.materials.len());
+                edited.materials.insert(name, MapMaterial::default());
+            }
+        });
+    });
+
+    if edited != map {
+        state.draft = Some(edited);
+    }
+}
+
+// moves the entities built from the draft, so dragging doesn't rebuild the level every
+// frame. Sizes and shapes show up once the draft is committed.
+fn preview_draft(state: Res<EditorState>, mut item_q: Query<(&MapItem, &mut Transform)>) {
+    let Some(draft) = state.draft.as_ref() else {
+        return;
+    };
+
+    for (item, mut transform) in item_q.iter_mut() {
+        let moved = match *item {
+            MapItem::Geometry(index) => draft.geometry.get(index).map(Geometry::transform),
+            MapItem::Light(index) => draft.lights.get(index).map(MapLight::transform),
+            MapItem::Trigger(index) => draft.triggers.get(index).map(Trigger::transform),
+        };
+        if let Some(moved) = moved {
+            if *transform != moved {
+                *transform = moved;
+            }
+        }
+    }
+}
+
+// writes the draft into the Map asset once the transform tool is confirmed and egui isn't
+// holding a drag or a text field
+fn commit_draft(
+    mut state: ResMut<EditorState>,
+    mut contexts: EguiContexts,
+    current: Res<CurrentMap>,
+    mut maps: ResMut<Assets<Map>>,
+    mut edit_in_place: ResMut<EditInPlace>,
+) {
+    let ctx = contexts.ctx_mut();
+    if state.draft.is_none()
+        || state.tool.is_some()
+        || ctx.is_using_pointer()
+        || ctx.wants_keyboard_input()
+    {
+        return;
+    }
+
+    if let Some(draft) = state.draft.take() {
+        write_draft(draft, &mut maps, &current, &mut edit_in_place);
+    }
+}
+
+fn write_draft(
+    draft: Map,
+    maps: &mut Assets<Map>,
+    current: &CurrentMap,
+    edit_in_place: &mut EditInPlace,
+) {
+    // get_mut alone counts as a modification, and a cancelled tool can leave a draft that
+    // is the same as the asset
+    if maps.get(current.0.id()).is_some_and(|map| *map != draft) {
+        if let Some(map) = maps.get_mut(current.0.id()) {
+            *map = draft;
+            edit_in_place.0 = true;
+        }
+    }
+}
+
+fn selection_ui(ui: &mut egui::Ui, map: &mut Map, selection: Selection) {
+    let material_names: Vec<String> = map.materials.keys().cloned().collect();
+
+    match selection {
+        Selection::Geometry(index) => {
+            let Some(geometry) = map.geometry.get_mut(index) else {
+                return;
+            };
+            vec3_ui(ui, "position", &mut geometry.position, 0.1);
+            vec3_ui(ui, "rotation", &mut geometry.rotation, 1.0);
+            match &mut geometry.shape {
+                Shape::Plane { size } => {
+                    ui.add(egui::DragValue::new(size).speed(0.1).prefix("size "));
+                }
+                Shape::Cuboid { size } | Shape::Ramp { size } => vec3_ui(ui, "size", size, 0.1),
+                Shape::Cylinder { radius, height } => {
+                    ui.horizontal(|ui| {
+                        ui.add(egui::DragValue::new(radius).speed(0.05).prefix("radius "));
+                        ui.add(egui::DragValue::new(height).speed(0.1).prefix("height "));
+                    });
+                }
+                Shape::Sphere { radius } => {
+                    ui.add(egui::DragValue::new(radius).speed(0.05).prefix("radius "));
+                }
+            }
+            egui::ComboBox::from_label("material")
+                .selected_text(geometry.material.clone())
+                .show_ui(ui, |ui| {
+                    for name in material_names {
+                        ui.selectable_value(&mut geometry.material, name.clone(), name);
+                    }
+                });
+        }
+        Selection::Light(index) => {
+            let Some(light) = map.lights.get_mut(index) else {
+                return;
+            };
+            ui.horizontal(|ui| {
+                ui.selectable_value(&mut light.kind, LightKind::Point, "Point");
+                ui.selectable_value(&mut light.kind, LightKind::Spot, "Spot");
+                ui.selectable_value(&mut light.kind, LightKind::Directional, "Directional");
+            });
+            vec3_ui(ui, "position", &mut light.position, 0.1);
+            vec3_ui(ui, "rotation", &mut light.rotation, 1.0);
+            ui.horizontal(|ui| {
+                ui.color_edit_button_rgb(&mut light.color);
+                ui.add(
+                    egui::DragValue::new(&mut light.intensity)
+                        .speed(10.0)
+                        .prefix("intensity "),
+                );
+                ui.add(
+                    egui::DragValue::new(&mut light.range)
+                        .speed(0.1)
+                        .prefix("range "),
+                );
+            });
+            ui.checkbox(&mut light.shadows, "shadows");
+        }
+        Selection::Trigger(index) => {
+            let Some(trigger) = map.triggers.get_mut(index) else {
+                return;
+            };
+            vec3_ui(ui, "position", &mut trigger.position, 0.1);
+            vec3_ui(ui, "rotation", &mut trigger.rotation, 1.0);
+            vec3_ui(ui, "size", &mut trigger.size, 0.1);
+            match &mut trigger.kind {
+                TriggerKind::JumpPad { velocity } => vec3_ui(ui, "launch", velocity, 0.1),
+                TriggerKind::SpeedPad { speed } => {
+                    ui.add(egui::DragValue::new(speed).speed(0.1).prefix("speed "));
+                }
+                TriggerKind::Teleporter {
+                    destination,
+                    yaw,
+                    keep_velocity,
+                } => {
+                    vec3_ui(ui, "destination", destination, 0.1);
+                    ui.horizontal(|ui| {
+                        let mut set_yaw = yaw.is_some();
+                        ui.checkbox(&mut set_yaw, "set yaw");
+                        match (set_yaw, yaw.as_mut()) {
+                            (true, Some(yaw)) => {
+                                ui.add(egui::DragValue::new(yaw).speed(1.0));
+                            }
+                            (true, None) => *yaw = Some(0.0),
+                            (false, _) => *yaw = None,
+                        }
+                    });
+                    ui.checkbox(keep_velocity, "keep velocity");
+                }
+                TriggerKind::Checkpoint { order } => {
+                    ui.add(egui::DragValue::new(order).prefix("order "));
+                }
+                TriggerKind::CourseStart | TriggerKind::CourseFinish => {}
+            }
+        }
+        Selection::PlayerSpawn(index) => {
+            let Some(spawn) = map.player_spawns.get_mut(index) else {
+                return;
+            };
+            vec3_ui(ui, "position", &mut spawn.position, 0.1);
+            ui.add(
+                egui::DragValue::new(&mut spawn.yaw)
+                    .speed(1.0)
+                    .prefix("yaw "),
+            );
+        }
+        Selection::TargetVolume(index) => {
+            let Some(volume) = map.target_volumes.get_mut(index) else {
+                return;
+            };
+            vec3_ui(ui, "min", &mut volume.min, 0.1);
+            vec3_ui(ui, "max", &mut volume.max, 0.1);
+            ui.add(egui::DragValue::new(&mut volume.count).prefix("targets "));
+            ui.add(
+                egui::DragValue::new(&mut volume.health)
+                    .clamp_range(1..=100)
+                    .prefix("health "),
+            );
+        }
+    }
+}
+
+fn vec3_ui(ui: &mut egui::Ui, label: &str, value: &mut [f32; 3], speed: f64) {
+    ui.horizontal(|ui| {
+        ui.label(label);
+        for component in value.iter_mut() {
+            ui.add(egui::DragValue::new(component).speed(speed));
+        }
+    });
+}
+
+fn draw_gizmos(
+    mut gizmos: Gizmos,
+    state: Res<EditorState>,
+    current: Res<CurrentMap>,
+    maps: Res<Assets<Map>>,
+) {
+    let Some(map) = working_map(&state, &maps, &current) else {
+        return;
+    };
+
+    for (selection, point) in markers(map) {
+        let color = if state.selected == Some(selection) {
+            Color::YELLOW
+        } else {
+            Color::WHITE
+        };
+
+        match selection {
+            Selection::PlayerSpawn(index) => {
+                gizmos.sphere(point, Quat::IDENTITY, MARKER_RADIUS, Color::GREEN);
+                let facing = map.player_spawns[index].rotation() * Vec3::NEG_Z;
+                gizmos.ray(point, facing * 1.5, color);
+            }
+            Selection::TargetVolume(index) => {
+                let volume = &map.target_volumes[index];
+                let size = (Vec3::from(volume.max) - Vec3::from(volume.min)).max(Vec3::splat(0.05));
+                gizmos.cuboid(Transform::from_translation(point).with_scale(size), color);
+                gizmos.sphere(point, Quat::IDENTITY, MARKER_RADIUS, Color::RED);
+            }
+            _ => {
+                gizmos.sphere(point, Quat::IDENTITY, MARKER_RADIUS, color);
+            }
+        }
+    }
+
+    let Some(selection) = state.selected else {
+        return;
+    };
+
+    if let Selection::Geometry(index) = selection {
+        if let Some(geometry) = map.geometry.get(index) {
+            let bounds = geometry
+                .transform()
+                .with_scale(shape_extents(&geometry.shape));
+            gizmos.cuboid(bounds, Color::YELLOW);
+        }
+    }
+
+    if let Selection::Trigger(index) = selection {
+        if let Some(trigger) = map.triggers.get(index) {
+            let bounds = trigger.transform().with_scale(trigger.size.into());
+            gizmos.cuboid(bounds, Color::YELLOW);
+            if let TriggerKind::Teleporter { destination, .. } = trigger.kind {
+                gizmos.line(trigger.position.into(), destination.into(), Color::PURPLE);
+                gizmos.sphere(
+                    destination.into(),
+                    Quat::IDENTITY,
+                    MARKER_RADIUS,
+                    Color::PURPLE,
+                );
+            }
+        }
+    }
+
+    if let Some(center) = center(map, selection) {
+        let locked = state.tool.as_ref().and_then(|tool| tool.axis);
+        for (index, axis) in AXES.iter().enumerate() {
+            if locked.is_none_or(|locked| locked == index) {
+                gizmos.ray(center, *axis * 2.0, AXIS_COLORS[index]);
+            }
+        }
+    }
+}
+
+fn markers(map: &Map) -> Vec<(Selection, Vec3)> {
+    let lights = map
+        .lights
+        .iter()
+        .enumerate()
+        .map(|(index, light)| (Selection::Light(index), Vec3::from(light.position)));
+    let spawns = map
+        .player_spawns
+        .iter()
+        .enumerate()
+        .map(|(index, spawn)| (Selection::PlayerSpawn(index), Vec3::from(spawn.position)));
+    let volumes = (0..map.target_volumes.len()).filter_map(|index| {
+        let selection = Selection::TargetVolume(index);
+        center(map, selection).map(|point| (selection, point))
+    });
+
+    lights.chain(spawns).chain(volumes).collect()
+}
+
+fn describe(map: &Map, selection: Selection) -> String {
+    match selection {
+        Selection::Geometry(index) => {
+            let shape = match map.geometry[index].shape {
+                Shape::Plane { .. } => "plane",
+                Shape::Cuboid { .. } => "cube",
+                Shape::Cylinder { .. } => "cylinder",
+                Shape::Sphere { .. } => "sphere",
+                Shape::Ramp { .. } => "ramp",
+            };
+            format!("{shape} #{index}")
+        }
+        Selection::Light(index) => format!("light #{index}"),
+        Selection::Trigger(index) => {
+            let kind = match map.triggers[index].kind {
+                TriggerKind::JumpPad { .. } => "jump pad",
+                TriggerKind::SpeedPad { .. } => "speed pad",
+                TriggerKind::Teleporter { .. } => "teleporter",
+                TriggerKind::CourseStart => "course start",
+                TriggerKind::Checkpoint { .. } => "checkpoint",
+                TriggerKind::CourseFinish => "course finish",
+            };
+            format!("{kind} #{index}")
+        }
+        Selection::PlayerSpawn(index) => format!("player spawn #{index}"),
+        Selection::TargetVolume(index) => format!("target spawner #{index}"),
+    }
+}
+
+fn shape_extents(shape: &Shape) -> Vec3 {
+    match *shape {
+        Shape::Plane { size } => Vec3::new(size, 0.2, size),
+        Shape::Cuboid { size } | Shape::Ramp { size } => size.into(),
+        Shape::Cylinder { radius, height } => Vec3::new(radius * 2.0, height, radius * 2.0),
+        Shape::Sphere { radius } => Vec3::splat(radius * 2.0),
+    }
+}
+
+fn center(map: &Map, selection: Selection) -> Option<Vec3> {
+    match selection {
+        Selection::Geometry(index) => map.geometry.get(index).map(|g| g.position.into()),
+        Selection::Light(index) => map.lights.get(index).map(|l| l.position.into()),
+        Selection::Trigger(index) => map.triggers.get(index).map(|t| t.position.into()),
+        Selection::PlayerSpawn(index) => map.player_spawns.get(index).map(|s| s.position.into()),
+        Selection::TargetVolume(index) => map
+            .target_volumes
+            .get(index)
+            .map(|v| (Vec3::from(v.min) + Vec3::from(v.max)) / 2.0),
+    }
+}
+
+fn translate(map: &mut Map, selection: Selection, offset: Vec3) {
+    let shift = |point: &mut [f32; 3]| *point = (Vec3::from(*point) + offset).into();
+
+    match selection {
+        Selection::Geometry(index) => {
+            if let Some(geometry) = map.geometry.get_mut(index) {
+                shift(&mut geometry.position);
+            }
+        }
+        Selection::Light(index) => {
+            if let Some(light) = map.lights.get_mut(index) {
+                shift(&mut light.position);
+            }
+        }
+        Selection::Trigger(index) => {
+            if let Some(trigger) = map.triggers.get_mut(index) {
+                shift(&mut trigger.position);
+            }
+        }
+        Selection::PlayerSpawn(index) => {
+            if let Some(spawn) = map.player_spawns.get_mut(index) {
+                shift(&mut spawn.position);
+            }
+        }
+        Selection::TargetVolume(index) => {
+            if let Some(volume) = map.target_volumes.get_mut(index) {
+                shift(&mut volume.min);
+                shift(&mut volume.max);
+            }
+        }
+    }
+}
+
+fn rotate(map: &mut Map, selection: Selection, axis: usize, degrees: f32) {
+    match selection {
+        Selection::Geometry(index) => {
+            if let Some(geometry) = map.geometry.get_mut(index) {
+                geometry.rotation[axis] += degrees;
+            }
+        }
+        Selection::Light(index) => {
+            if let Some(light) = map.lights.get_mut(index) {
+                light.rotation[axis] += degrees;
+            }
+        }
+        Selection::Trigger(index) => {
+            if let Some(trigger) = map.triggers.get_mut(index) {
+                trigger.rotation[axis] += degrees;
+            }
+        }
+        Selection::PlayerSpawn(index) => {
+            if let Some(spawn) = map.player_spawns.get_mut(index) {
+                spawn.yaw += degrees;
+            }
+        }
+        // target volumes are axis aligned boxes
+        Selection::TargetVolume(_) => {}
+    }
+}
+
+fn scale(map: &mut Map, selection: Selection, axis: Option<usize>, factor: f32) {
+    match selection {
+        Selection::Geometry(index) => {
+            if let Some(geometry) = map.geometry.get_mut(index) {
+                scale_shape(&mut geometry.shape, axis, factor);
+            }
+        }
+        Selection::Light(index) => {
+            if let Some(light) = map.lights.get_mut(index) {
+                light.intensity *= factor;
+            }
+        }
+        Selection::Trigger(index) => {
+            if let Some(trigger) = map.triggers.get_mut(index) {
+                match axis {
+                    Some(axis) => trigger.size[axis] *= factor,
+                    None => trigger.size.iter_mut().for_each(|side| *side *= factor),
+                }
+            }
+        }
+        Selection::PlayerSpawn(_) => {}
+        Selection::TargetVolume(index) => {
+            if let Some(volume) = map.target_volumes.get_mut(index) {
+                let (min, max) = (Vec3::from(volume.min), Vec3::from(volume.max));
+                let center = (min + max) / 2.0;
+                let mut factors = Vec3::ONE;
+                match axis {
+                    Some(axis) => factors[axis] = factor,
+                    None => factors = Vec3::splat(factor),
+                }
+                let half_size = (max - min) / 2.0 * factors;
+                volume.min = (center - half_size).into();
+                volume.max = (center + half_size).into();
+            }
+        }
+    }
+}
+
+fn scale_shape(shape: &mut Shape, axis: Option<usize>, factor: f32) {
+    match shape {
+        Shape::Plane { size } => *size *= factor,
+        Shape::Cuboid { size } | Shape::Ramp { size } => match axis {
+            Some(axis) => size[axis] *= factor,
+            None => size.iter_mut().for_each(|side| *side *= factor),
+        },
+        Shape::Cylinder { radius, height } => match axis {
+            Some(1) => *height *= factor,
+            Some(_) => *radius *= factor,
+            None => {
+                *radius *= factor;
+                *height *= factor;
+            }
+        },
+        Shape::Sphere { radius } => *radius *= factor,
+    }
+}
+
+fn remove(map: &mut Map, selection: Selection) {
+    match selection {
+        Selection::Geometry(index) if index < map.geometry.len() => {
+            map.geometry.remove(index);
+        }
+        Selection::Light(index) if index < map.lights.len() => {
+            map.lights.remove(index);
+        }
+        Selection::Trigger(index) if index < map.triggers.len() => {
+            map.triggers.remove(index);
+        }
+        Selection::PlayerSpawn(index) if index < map.player_spawns.len() => {
+            map.player_spawns.remove(index);
+        }
+        Selection::TargetVolume(index) if index < map.target_volumes.len() => {
+            map.target_volumes.remove(index);
+        }
+        _ => {}
+    }
+}
+
+fn duplicate(map: &mut Map, selection: Selection) -> Option<Selection> {
+    match selection {
+        Selection::Geometry(index) => {
+            let copy = map.geometry.get(index)?.clone();
+            map.geometry.push(copy);
+            Some(Selection::Geometry(map.geometry.len() - 1))
+        }
+        Selection::Light(index) => {
+            let copy = map.lights.get(index)?.clone();
+            map.lights.push(copy);
+            Some(Selection::Light(map.lights.len() - 1))
+        }
+        Selection::Trigger(index) => {
+            let copy = map.triggers.get(index)?.clone();
+            map.triggers.push(copy);
+            Some(Selection::Trigger(map.triggers.len() - 1))
+        }
+        Selection::PlayerSpawn(index) => {
+            let copy = map.player_spawns.get(index)?.clone();
+            map.player_spawns.push(copy);
+            Some(Selection::PlayerSpawn(map.player_spawns.len() - 1))
+        }
+        Selection::TargetVolume(index) => {
+            let copy = map.target_volumes.get(index)?.clone();
+            map.target_volumes.push(copy);
+            Some(Selection::TargetVolume(map.target_volumes.len() - 1))
+        }
+    }
+}
+
+fn save_map(map: &Map, path: &str) -> Result<(), String> {
+    let text = ron::ser::to_string_pretty(map, ron::ser::PrettyConfig::default())
+        .map_err(|err| err.to_string())?;
+    std::fs::write(path, text).map_err(|err| err.to_string())
+}
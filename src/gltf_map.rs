@@ -0,0 +1,231 @@
+use bevy::{
+    gltf::GltfExtras,
+    prelude::*,
+    render::{primitives::Aabb, view::VisibilitySystems},
+    scene::SceneInstanceReady,
+    transform::TransformSystem,
+};
+use bevy_rapier3d::prelude::*;
+use serde::Deserialize;
+
+use crate::{
+    map::{PlayerSpawn, TargetVolume},
+    world::{ImportedScene, MapLoaded, SpawnPoints},
+};
+
+// Colliders for glTF maps are picked from the name of the node that owns the mesh:
+//   "Wall_col_box"    -> box fitted to the mesh bounds
+//   "Pillar_col_hull" -> convex hull of the mesh
+//   "Ball_col_sphere" -> sphere fitted to the mesh bounds
+//   "Grass_nocol"     -> no collider
+//   anything else     -> trimesh (static geometry)
+// adding "_hidden" to a name keeps the collider but hides the mesh, for invisible walls.
+//
+// Lights and spawns come from node custom properties (glTF extras), e.g.
+//   {"light": "point", "intensity": 800, "color": [1.0, 0.9, 0.8], "range": 30, "shadows": true}
+//   {"spawn": "player", "yaw": 90}
+//   {"spawn": "targets", "size": [0, 8, 8], "count": 3, "health": 2}
+pub struct GltfMapPlugin;
+
+impl Plugin for GltfMapPlugin {
+    fn build(&self, app: &mut App) {
+        // scene entities only have correct GlobalTransforms after propagation, and mesh
+        // bounds once they've been calculated
+        app.add_systems(
+            PostUpdate,
+            build_scene_colliders
+                .after(TransformSystem::TransformPropagate)
+                .after(VisibilitySystems::CalculateBoundsFlush),
+        );
+    }
+}
+
+#[derive(Deserialize, Default)]
+struct NodeExtras {
+    light: Option<String>,
+    intensity: Option<f32>,
+    color: Option<[f32; 3]>,
+    range: Option<f32>,
+    shadows: Option<bool>,
+    spawn: Option<String>,
+    yaw: Option<f32>,
+    size: Option<[f32; 3]>,
+    count: Option<u32>,
+    health: Option<u32>,
+}
+
+enum ColliderKind {
+    None,
+    Box,
+    Sphere,
+    Hull,
+    TriMesh,
+}
+
+fn collider_kind(name: &str) -> ColliderKind {
+    if name.contains("_nocol") {
+        ColliderKind::None
+    } else if name.contains("_col_box") {
+        ColliderKind::Box
+    } else if name.contains("_col_sphere") {
+        ColliderKind::Sphere
+    } else if name.contains("_col_hull") {
+        ColliderKind::Hull
+    } else {
+        ColliderKind::TriMesh
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn build_scene_colliders(
+    mut commands: Commands,
+    mut ready: EventReader<SceneInstanceReady>,
+    scene_q: Query<(), With<ImportedScene>>,
+    children_q: Query<&Children>,
+    node_q: Query<(Option<&Name>, Option<&GltfExtras>, &GlobalTransform)>,
+    mesh_q: Query<(&Handle<Mesh>, Option<&Aabb>, &Parent)>,
+    meshes: Res<Assets<Mesh>>,
+    mut spawn_points: ResMut<SpawnPoints>,
+    mut map_loaded: EventWriter<MapLoaded>,
+) {
+    for SceneInstanceReady { parent } in ready.read() {
+        if scene_q.get(*parent).is_err() {
+            continue;
+        }
+
+        let mut added_spawns = false;
+
+        for entity in children_q.iter_descendants(*parent) {
+            // mesh primitives are children of the node that carries the name
+            if let Ok((mesh_handle, aabb, node)) = mesh_q.get(entity) {
+                let name = node_q
+                    .get(node.get())
+                    .ok()
+                    .and_then(|(name, _, _)| name)
+                    .map(|name| name.as_str())
+                    .unwrap_or_default();
+
+                // only boxes and spheres need the bounds, worked out here if bevy hasn't yet
+                let bounds = || {
+                    aabb.copied()
+                        .or_else(|| meshes.get(mesh_handle)?.compute_aabb())
+                        .map(|aabb| (Vec3::from(aabb.center), Vec3::from(aabb.half_extents)))
+                };
+
+                let collider = match collider_kind(name) {
+                    ColliderKind::None => None,
+                    ColliderKind::Box => bounds().map(|(center, half_extents)| {
+                        Collider::compound(vec![(
+                            center,
+                            Quat::IDENTITY,
+                            Collider::cuboid(half_extents.x, half_extents.y, half_extents.z),
+                        )])
+                    }),
+                    ColliderKind::Sphere => bounds().map(|(center, half_extents)| {
+                        Collider::compound(vec![(
+                            center,
+                            Quat::IDENTITY,
+                            Collider::ball(half_extents.max_element()),
+                        )])
+                    }),
+                    ColliderKind::Hull => meshes.get(mesh_handle).and_then(|mesh| {
+                        Collider::from_bevy_mesh(mesh, &ComputedColliderShape::ConvexHull)
+                    }),
+                    ColliderKind::TriMesh => meshes.get(mesh_handle).and_then(|mesh| {
+                        Collider::from_bevy_mesh(mesh, &ComputedColliderShape::TriMesh)
+                    }),
+                };
+
+                if let Some(collider) = collider {
+                    commands.entity(entity).insert((RigidBody::Fixed, collider));
+                }
+
+                if name.contains("_hidden") {
+                    commands.entity(entity).insert(Visibility::Hidden);
+                }
+            }
+
+            let Ok((_, Some(extras), global_transform)) = node_q.get(entity) else {
+                continue;
+            };
+
+            let Ok(extras) = serde_json::from_str::<NodeExtras>(&extras.value) else {
+                warn!("ignoring unreadable glTF extras: {}", extras.value);
+                continue;
+            };
+
+            if let Some(kind) = extras.light.as_deref() {
+                let [r, g, b] = extras.color.unwrap_or([1.0, 1.0, 1.0]);
+                let color = Color::rgb(r, g, b);
+                let shadows_enabled = extras.shadows.unwrap_or(false);
+
+                // lights are parented to the node so they follow it
+                commands.entity(entity).with_children(|parent| match kind {
+                    "spot" => {
+                        parent.spawn(SpotLightBundle {
+                            spot_light: SpotLight {
+                                color,
+                                intensity: extras.intensity.unwrap_or(800.0),
+                                range: extras.range.unwrap_or(20.0),
+                                shadows_enabled,
+                                ..default()
+                            },
+                            ..default()
+                        });
+                    }
+                    "directional" | "sun" => {
+                        parent.spawn(DirectionalLightBundle {
+                            directional_light: DirectionalLight {
+                                color,
+                                illuminance: extras.intensity.unwrap_or(10000.0),
+                                shadows_enabled,
+                                ..default()
+                            },
+                            ..default()
+                        });
+                    }
+                    _ => {
+                        parent.spawn(PointLightBundle {
+                            point_light: PointLight {
+                                color,
+                                intensity: extras.intensity.unwrap_or(800.0),
+                                range: extras.range.unwrap_or(20.0),
+                                shadows_enabled,
+                                ..default()
+                            },
+                            ..default()
+                        });
+                    }
+                });
+            }
+
+            let position = global_transform.translation();
+
+            match extras.spawn.as_deref() {
+                Some("player") => {
+                    spawn_points.player.push(PlayerSpawn {
+                        position: position.into(),
+                        yaw: extras.yaw.unwrap_or(0.0),
+                    });
+                    added_spawns = true;
+                }
+                Some("targets") => {
+                    let half_size = Vec3::from(extras.size.unwrap_or([1.0, 1.0, 1.0])) / 2.0;
+                    spawn_points.targets.push(TargetVolume {
+                        min: (position - half_size).into(),
+                        max: (position + half_size).into(),
+                        count: extras.count.unwrap_or(1),
+                        health: extras.health.unwrap_or(1),
+                    });
+                    added_spawns = true;
+                }
+                Some(other) => warn!("unknown spawn kind in glTF extras: {other}"),
+                None => {}
+            }
+        }
+
+        if added_spawns {
+            map_loaded.send(MapLoaded);
+        }
+    }
+}
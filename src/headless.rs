@@ -0,0 +1,30 @@
+use bevy::{prelude::*, scene::ScenePlugin};
+use bevy_rapier3d::prelude::*;
+
+// What the game plugins need from bevy when there's no window, GPU or sound card: assets,
+// transforms, scenes and physics. Maps and targets still make meshes and materials, nothing
+// draws them. Goes next to MinimalPlugins, then PlayerPlugin, WorldPlugin, JumboTilePlugin
+// and the other logic plugins run as usual. The dedicated server, the game's --headless
+// mode and the tests use it.
+//
+// The windowed game adds the rest itself: PlayerUiPlugin, SoundPlugin and the panels.
+pub struct HeadlessPlugin;
+
+impl Plugin for HeadlessPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins((
+            AssetPlugin::default(),
+            TransformPlugin,
+            HierarchyPlugin,
+            ScenePlugin,
+            RapierPhysicsPlugin::<NoUserData>::default(),
+        ))
+        .init_asset::<Mesh>()
+        .init_asset::<StandardMaterial>()
+        // same as the client
+        .insert_resource(RapierConfiguration {
+            gravity: Vec3::from((0.0, -10.0, 0.0)),
+            ..default()
+        });
+    }
+}
@@ -0,0 +1,179 @@
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    editor::editor_closed,
+    player::{game_paused, TargetHit},
+    settings::{load_settings, save_settings},
+    sound::{Sound, Sounds},
+};
+
+// The X that flashes around the crosshair when a shot lands, plus the hit/kill sounds
+// (their files and volumes are in sound.rs).
+pub struct HitMarkerPlugin;
+
+impl Plugin for HitMarkerPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(load_settings::<HitMarkerSettings>(SETTINGS_FILE))
+            .init_resource::<ActiveMarker>()
+            .add_systems(
+                Update,
+                (
+                    hit_marker_panel.run_if(game_paused).run_if(editor_closed),
+                    play_hits,
+                    draw_hit_marker,
+                )
+                    .chain(),
+            );
+    }
+}
+
+const SETTINGS_FILE: &str = "hitmarker.ron";
+
+// sizes are in pixels
+#[derive(Resource, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct HitMarkerSettings {
+    pub enabled: bool,
+    pub hit_color: [f32; 3],
+    pub headshot_color: [f32; 3],
+    pub kill_color: [f32; 3],
+    // seconds the marker takes to fade out
+    pub duration: f32,
+    // distance from the center to where the lines start
+    pub gap: f32,
+    pub length: f32,
+    pub thickness: f32,
+}
+
+impl Default for HitMarkerSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            hit_color: [1.0, 1.0, 1.0],
+            headshot_color: [1.0, 0.85, 0.2],
+            kill_color: [1.0, 0.2, 0.2],
+            duration: 0.25,
+            gap: 8.0,
+            length: 8.0,
+            thickness: 2.0,
+        }
+    }
+}
+
+// only the latest hit is shown
+#[derive(Resource, Default)]
+struct ActiveMarker {
+    hit: Option<TargetHit>,
+    // seconds since the hit
+    age: f32,
+}
+
+fn play_hits(
+    mut hits: EventReader<TargetHit>,
+    mut marker: ResMut<ActiveMarker>,
+    sounds: Sounds,
+    time: Res<Time>,
+    mut commands: Commands,
+) {
+    marker.age += time.delta_seconds();
+
+    for hit in hits.read() {
+        marker.hit = Some(*hit);
+        marker.age = 0.0;
+
+        let sound = if hit.kill { Sound::Kill } else { Sound::Hit };
+        // from the target, so you can hear where it was
+        sounds.spatial(&mut commands, sound, hit.point);
+    }
+}
+
+fn draw_hit_marker(
+    mut contexts: EguiContexts,
+    marker: Res<ActiveMarker>,
+    settings: Res<HitMarkerSettings>,
+) {
+    let Some(hit) = marker.hit else {
+        return;
+    };
+    if !settings.enabled || marker.age >= settings.duration {
+        return;
+    }
+
+    // headshots first, most targets die to a single hit, so a headshot is usually a kill too
+    // (it still gets the bigger kill marker below)
+    let color = if hit.headshot {
+        settings.headshot_color
+    } else if hit.kill {
+        settings.kill_color
+    } else {
+        settings.hit_color
+    };
+    let fade = 1.0 - marker.age / settings.duration.max(f32::EPSILON);
+    let [r, g, b] = color.map(|c| (c * 255.0).round() as u8);
+    let color = egui::Color32::from_rgba_unmultiplied(r, g, b, (fade * 255.0) as u8);
+
+    // kills get a bigger marker that also grows a bit as it fades
+    let (length, thickness) = if hit.kill {
+        (
+            settings.length * (1.5 + 0.5 * (1.0 - fade)),
+            settings.thickness * 1.5,
+        )
+    } else {
+        (settings.length, settings.thickness)
+    };
+
+    let ctx = contexts.ctx_mut();
+    let painter = ctx.layer_painter(egui::LayerId::new(
+        egui::Order::Foreground,
+        egui::Id::new("hit marker"),
+    ));
+    let center = ctx.screen_rect().center();
+    for (x, y) in [(1.0, 1.0), (1.0, -1.0), (-1.0, 1.0), (-1.0, -1.0)] {
+        let direction = egui::vec2(x, y).normalized();
+        painter.line_segment(
+            [
+                center + direction * settings.gap,
+                center + direction * (settings.gap + length),
+            ],
+            egui::Stroke::new(thickness, color),
+        );
+    }
+}
+
+fn hit_marker_panel(mut contexts: EguiContexts, mut settings: ResMut<HitMarkerSettings>) {
+    // edit a copy so change detection only fires on real edits
+    let mut edited = settings.clone();
+
+    egui::Window::new("Hit markers").show(contexts.ctx_mut(), |ui| {
+        ui.checkbox(&mut edited.enabled, "Show hit markers");
+        for (label, color) in [
+            ("Hit", &mut edited.hit_color),
+            ("Headshot", &mut edited.headshot_color),
+            ("Kill", &mut edited.kill_color),
+        ] {
+            ui.horizontal(|ui| {
+                ui.label(label);
+                ui.color_edit_button_rgb(color);
+            });
+        }
+        ui.add(egui::Slider::new(&mut edited.duration, 0.05..=1.0).text("duration"));
+        ui.add(egui::Slider::new(&mut edited.gap, 0.0..=30.0).text("gap"));
+        ui.add(egui::Slider::new(&mut edited.length, 1.0..=30.0).text("length"));
+        ui.add(egui::Slider::new(&mut edited.thickness, 0.5..=6.0).text("thickness"));
+
+        ui.horizontal(|ui| {
+            if ui.button("Save").clicked() {
+                save_settings(SETTINGS_FILE, &edited);
+            }
+            if ui.button("Reset").clicked() {
+                edited = HitMarkerSettings::default();
+            }
+        });
+    });
+
+    if edited != *settings {
+        *settings = edited;
+    }
+}
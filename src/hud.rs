@@ -0,0 +1,409 @@
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts};
+use bevy_rapier3d::prelude::Velocity;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    editor::editor_closed,
+    player::{game_paused, Ammo, Player, RocketCooldown, ShootCooldown, ShotFired, TargetHit},
+    rng::GameRng,
+    settings::{load_settings, save_settings},
+    world::MapLoaded,
+};
+
+pub struct HudPlugin;
+
+impl Plugin for HudPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(load_settings::<HudLayout>(LAYOUT_FILE))
+            .init_resource::<SessionStats>()
+            .add_systems(
+                Update,
+                (
+                    hud_panel.run_if(game_paused).run_if(editor_closed),
+                    build_hud,
+                    update_stats,
+                    update_hud,
+                )
+                    .chain(),
+            );
+    }
+}
+
+const LAYOUT_FILE: &str = "hud.ron";
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub enum Anchor {
+    TopLeft,
+    Top,
+    TopRight,
+    Left,
+    Center,
+    Right,
+    BottomLeft,
+    Bottom,
+    BottomRight,
+}
+
+const ANCHORS: [Anchor; 9] = [
+    Anchor::TopLeft,
+    Anchor::Top,
+    Anchor::TopRight,
+    Anchor::Left,
+    Anchor::Center,
+    Anchor::Right,
+    Anchor::BottomLeft,
+    Anchor::Bottom,
+    Anchor::BottomRight,
+];
+
+impl Anchor {
+    fn align(self) -> (JustifyContent, AlignItems) {
+        use Anchor::*;
+        let horizontal = match self {
+            TopLeft | Left | BottomLeft => JustifyContent::FlexStart,
+            Top | Center | Bottom => JustifyContent::Center,
+            TopRight | Right | BottomRight => JustifyContent::FlexEnd,
+        };
+        let vertical = match self {
+            TopLeft | Top | TopRight => AlignItems::FlexStart,
+            Left | Center | Right => AlignItems::Center,
+            BottomLeft | Bottom | BottomRight => AlignItems::FlexEnd,
+        };
+        (horizontal, vertical)
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct HudElement {
+    pub visible: bool,
+    pub anchor: Anchor,
+    // pixels in from the screen edges the anchor is on
+    pub offset: [f32; 2],
+}
+
+impl HudElement {
+    const fn new(anchor: Anchor, x: f32, y: f32) -> Self {
+        Self {
+            visible: true,
+            anchor,
+            offset: [x, y],
+        }
+    }
+}
+
+#[derive(Resource, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct HudLayout {
+    pub font_size: f32,
+    pub speed: HudElement,
+    pub cooldowns: HudElement,
+    pub ammo: HudElement,
+    pub timer: HudElement,
+    pub score: HudElement,
+    pub accuracy: HudElement,
+}
+
+impl Default for HudLayout {
+    fn default() -> Self {
+        Self {
+            font_size: 24.0,
+            speed: HudElement::new(Anchor::Bottom, 0.0, 90.0),
+            cooldowns: HudElement::new(Anchor::Bottom, 0.0, 30.0),
+            ammo: HudElement::new(Anchor::BottomRight, 30.0, 30.0),
+            timer: HudElement::new(Anchor::TopLeft, 20.0, 20.0),
+            score: HudElement::new(Anchor::TopRight, 20.0, 20.0),
+            accuracy: HudElement::new(Anchor::TopRight, 20.0, 50.0),
+        }
+    }
+}
+
+#[derive(Component, Clone, Copy, PartialEq)]
+enum HudItem {
+    Speed,
+    Cooldowns,
+    Ammo,
+    Timer,
+    Score,
+    Accuracy,
+}
+
+const HUD_ITEMS: [HudItem; 6] = [
+    HudItem::Speed,
+    HudItem::Cooldowns,
+    HudItem::Ammo,
+    HudItem::Timer,
+    HudItem::Score,
+    HudItem::Accuracy,
+];
+
+impl HudItem {
+    fn name(self) -> &'static str {
+        match self {
+            HudItem::Speed => "Speed",
+            HudItem::Cooldowns => "Cooldowns",
+            HudItem::Ammo => "Ammo",
+            HudItem::Timer => "Session timer",
+            HudItem::Score => "Score",
+            HudItem::Accuracy => "Accuracy",
+        }
+    }
+
+    fn element(self, layout: &HudLayout) -> HudElement {
+        match self {
+            HudItem::Speed => layout.speed,
+            HudItem::Cooldowns => layout.cooldowns,
+            HudItem::Ammo => layout.ammo,
+            HudItem::Timer => layout.timer,
+            HudItem::Score => layout.score,
+            HudItem::Accuracy => layout.accuracy,
+        }
+    }
+
+    fn element_mut(self, layout: &mut HudLayout) -> &mut HudElement {
+        match self {
+            HudItem::Speed => &mut layout.speed,
+            HudItem::Cooldowns => &mut layout.cooldowns,
+            HudItem::Ammo => &mut layout.ammo,
+            HudItem::Timer => &mut layout.timer,
+            HudItem::Score => &mut layout.score,
+            HudItem::Accuracy => &mut layout.accuracy,
+        }
+    }
+}
+
+// shots and hits since the map was (re)loaded
+#[derive(Resource, Default)]
+pub struct SessionStats {
+    pub started: f64,
+    pub shots: u32,
+    pub hits: u32,
+    pub headshots: u32,
+    pub kills: u32,
+    // scores only compare between sessions with the same targets
+    pub seed: u64,
+}
+
+impl SessionStats {
+    pub fn accuracy(&self) -> f32 {
+        if self.shots == 0 {
+            0.0
+        } else {
+            self.hits as f32 / self.shots as f32
+        }
+    }
+}
+
+// every top level hud node, rebuilt when the layout changes
+#[derive(Component)]
+struct HudRoot;
+
+#[derive(Component, Clone, Copy)]
+enum CooldownFill {
+    Rocket,
+    Shoot,
+}
+
+fn build_hud(mut commands: Commands, layout: Res<HudLayout>, old_q: Query<Entity, With<HudRoot>>) {
+    if !layout.is_changed() {
+        return;
+    }
+
+    for entity in old_q.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    let text_style = TextStyle {
+        font_size: layout.font_size,
+        color: Color::WHITE,
+        ..default()
+    };
+
+    for item in HUD_ITEMS {
+        let element = item.element(&layout);
+        if !element.visible {
+            continue;
+        }
+
+        let (justify_content, align_items) = element.anchor.align();
+        let [x, y] = element.offset;
+        let container = (
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    justify_content,
+                    align_items,
+                    padding: UiRect::new(Val::Px(x), Val::Px(x), Val::Px(y), Val::Px(y)),
+                    ..default()
+                },
+                ..default()
+            },
+            HudRoot,
+        );
+
+        commands.spawn(container).with_children(|parent| {
+            if item == HudItem::Cooldowns {
+                parent
+                    .spawn(NodeBundle {
+                        style: Style {
+                            flex_direction: FlexDirection::Column,
+                            row_gap: Val::Px(4.0),
+                            ..default()
+                        },
+                        ..default()
+                    })
+                    .with_children(|parent| {
+                        cooldown_bar(parent, CooldownFill::Shoot, Color::ORANGE);
+                        cooldown_bar(parent, CooldownFill::Rocket, Color::CYAN);
+                    });
+            } else {
+                parent.spawn((TextBundle::from_section("", text_style.clone()), item));
+            }
+        });
+    }
+}
+
+fn cooldown_bar(parent: &mut ChildBuilder, fill: CooldownFill, color: Color) {
+    let background = NodeBundle {
+        style: Style {
+            width: Val::Px(160.0),
+            height: Val::Px(8.0),
+            ..default()
+        },
+        background_color: Color::rgba(0.0, 0.0, 0.0, 0.5).into(),
+        ..default()
+    };
+
+    parent.spawn(background).with_children(|parent| {
+        parent.spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    ..default()
+                },
+                background_color: color.into(),
+                ..default()
+            },
+            fill,
+        ));
+    });
+}
+
+fn update_stats(
+    mut stats: ResMut<SessionStats>,
+    mut map_loaded: EventReader<MapLoaded>,
+    mut shots: EventReader<ShotFired>,
+    mut hits: EventReader<TargetHit>,
+    time: Res<Time>,
+    rng: Res<GameRng>,
+) {
+    if map_loaded.read().next().is_some() {
+        *stats = SessionStats {
+            started: time.elapsed_seconds_f64(),
+            seed: rng.seed(),
+            ..default()
+        };
+    }
+
+    stats.shots += shots.read().count() as u32;
+    for hit in hits.read() {
+        stats.hits += 1;
+        if hit.headshot {
+            stats.headshots += 1;
+        }
+        if hit.kill {
+            stats.kills += 1;
+        }
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn update_hud(
+    stats: Res<SessionStats>,
+    time: Res<Time>,
+    player_q: Query<(&Velocity, &RocketCooldown, &ShootCooldown, &Ammo), With<Player>>,
+    mut text_q: Query<(&mut Text, &HudItem)>,
+    mut fill_q: Query<(&mut Style, &CooldownFill)>,
+) {
+    let Ok((velocity, rocket_cooldown, shoot_cooldown, ammo)) = player_q.get_single() else {
+        return;
+    };
+
+    for (mut text, item) in text_q.iter_mut() {
+        let value = match item {
+            HudItem::Speed => format!("{:.1} m/s", velocity.linvel.xz().length()),
+            HudItem::Ammo if ammo.reloading => "RELOADING".to_string(),
+            HudItem::Ammo => match ammo.capacity {
+                Some(capacity) => format!("{} / {}", ammo.loaded, capacity),
+                None => "Ammo unlimited".to_string(),
+            },
+            HudItem::Timer => {
+                let seconds = (time.elapsed_seconds_f64() - stats.started).max(0.0) as u32;
+                format!("{}:{:02}", seconds / 60, seconds % 60)
+            }
+            HudItem::Score => format!(
+                "Score {} ({} HS), seed {}",
+                stats.kills, stats.headshots, stats.seed
+            ),
+            HudItem::Accuracy => format!("Accuracy {:.0}%", stats.accuracy() * 100.0),
+            HudItem::Cooldowns => continue,
+        };
+        if text.sections[0].value != value {
+            text.sections[0].value = value;
+        }
+    }
+
+    for (mut style, fill) in fill_q.iter_mut() {
+        let timer = match fill {
+            CooldownFill::Rocket => &rocket_cooldown.timer,
+            CooldownFill::Shoot => &shoot_cooldown.timer,
+        };
+        let width = Val::Percent(timer.percent() * 100.0);
+        if style.width != width {
+            style.width = width;
+        }
+    }
+}
+
+fn hud_panel(mut contexts: EguiContexts, mut layout: ResMut<HudLayout>) {
+    // edit a copy so the hud is only rebuilt when something actually changed
+    let mut edited = layout.clone();
+
+    egui::Window::new("HUD").show(contexts.ctx_mut(), |ui| {
+        ui.add(egui::Slider::new(&mut edited.font_size, 10.0..=48.0).text("font size"));
+        ui.separator();
+
+        egui::Grid::new("hud elements").show(ui, |ui| {
+            for item in HUD_ITEMS {
+                let element = item.element_mut(&mut edited);
+                ui.checkbox(&mut element.visible, item.name());
+                egui::ComboBox::from_id_source(item.name())
+                    .selected_text(format!("{:?}", element.anchor))
+                    .show_ui(ui, |ui| {
+                        for anchor in ANCHORS {
+                            ui.selectable_value(&mut element.anchor, anchor, format!("{anchor:?}"));
+                        }
+                    });
+                ui.add(egui::DragValue::new(&mut element.offset[0]).prefix("x "));
+                ui.add(egui::DragValue::new(&mut element.offset[1]).prefix("y "));
+                ui.end_row();
+            }
+        });
+
+        ui.horizontal(|ui| {
+            if ui.button("Save").clicked() {
+                save_settings(LAYOUT_FILE, &edited);
+            }
+            if ui.button("Reset").clicked() {
+                edited = HudLayout::default();
+            }
+        });
+    });
+
+    if edited != *layout {
+        *layout = edited;
+    }
+}
@@ -0,0 +1,96 @@
+use bevy::{
+    input::{mouse::MouseMotion, InputSystem},
+    prelude::*,
+};
+
+pub struct GameInputPlugin;
+
+impl Plugin for GameInputPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<InputFrame>()
+            .add_systems(PreUpdate, gather_input.after(InputSystem));
+    }
+}
+
+// Everything player_input reads in one frame. It's filled from the keyboard and mouse here,
+// demo playback overwrites it with recorded frames right after.
+#[derive(Resource, Default, Clone, Copy, PartialEq, Debug)]
+pub struct InputFrame {
+    pub held: u16,
+    // buttons that went down this frame
+    pub pressed: u16,
+    // summed mouse motion
+    pub look: Vec2,
+    pub dt: f32,
+}
+
+impl InputFrame {
+    pub fn held(&self, button: u16) -> bool {
+        self.held & button != 0
+    }
+
+    pub fn just_pressed(&self, button: u16) -> bool {
+        self.pressed & button != 0
+    }
+}
+
+pub const FORWARD: u16 = 1 << 0;
+pub const BACK: u16 = 1 << 1;
+pub const LEFT: u16 = 1 << 2;
+pub const RIGHT: u16 = 1 << 3;
+pub const JUMP: u16 = 1 << 4;
+pub const SPRINT: u16 = 1 << 5;
+pub const SHOOT: u16 = 1 << 6;
+pub const ROCKET: u16 = 1 << 7;
+pub const PAUSE: u16 = 1 << 8;
+pub const RELOAD: u16 = 1 << 9;
+
+const KEY_BINDINGS: [(u16, KeyCode); 8] = [
+    (FORWARD, KeyCode::W),
+    (BACK, KeyCode::S),
+    (LEFT, KeyCode::A),
+    (RIGHT, KeyCode::D),
+    (JUMP, KeyCode::Space),
+    (SPRINT, KeyCode::ShiftLeft),
+    (PAUSE, KeyCode::Escape),
+    (RELOAD, KeyCode::R),
+];
+
+const MOUSE_BINDINGS: [(u16, MouseButton); 2] =
+    [(SHOOT, MouseButton::Left), (ROCKET, MouseButton::Right)];
+
+pub fn gather_input(
+    keys: Res<Input<KeyCode>>,
+    mouse_buttons: Res<Input<MouseButton>>,
+    mut motion_evr: EventReader<MouseMotion>,
+    time: Res<Time>,
+    mut input: ResMut<InputFrame>,
+) {
+    let mut held = 0;
+    let mut pressed = 0;
+
+    for (button, key) in KEY_BINDINGS {
+        if keys.pressed(key) {
+            held |= button;
+        }
+        if keys.just_pressed(key) {
+            pressed |= button;
+        }
+    }
+
+    for (button, mouse_button) in MOUSE_BINDINGS {
+        if mouse_buttons.pressed(mouse_button) {
+            held |= button;
+        }
+        if mouse_buttons.just_pressed(mouse_button) {
+            pressed |= button;
+        }
+    }
+
+    *input = InputFrame {
+        held,
+        pressed,
+        look: motion_evr.read().map(|ev| ev.delta).sum(),
+        dt: time.delta_seconds(),
+    };
+}
@@ -1,46 +1,88 @@
 use bevy::prelude::*;
 use bevy_rapier3d::prelude::*;
 
+use crate::{
+    rng::GameRng,
+    world::{MapEntity, MapLoaded, SpawnPoints},
+};
+
 pub struct JumboTilePlugin;
 
 impl Plugin for JumboTilePlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Startup, spawn_tiles);
+        app.add_systems(Update, spawn_tiles);
     }
 }
 
 #[derive(Component)]
 pub struct Kovaak;
 
-fn spawn_tiles(
+#[derive(Component)]
+pub struct TargetHealth {
+    pub max: u32,
+    pub left: u32,
+}
+
+// the box a target was spawned in, it gets moved somewhere else inside it when shot
+#[derive(Component, Clone, Copy)]
+pub struct SpawnVolume {
+    pub min: Vec3,
+    pub max: Vec3,
+}
+
+impl SpawnVolume {
+    pub fn random_point(&self, rng: &mut fastrand::Rng) -> Vec3 {
+        self.min + (self.max - self.min) * Vec3::new(rng.f32(), rng.f32(), rng.f32())
+    }
+}
+
+pub fn spawn_tiles(
     mut commands: Commands,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<StandardMaterial>>,
+    mut events: EventReader<MapLoaded>,
+    spawn_points: Res<SpawnPoints>,
+    old_q: Query<Entity, With<Kovaak>>,
+    mut rng: ResMut<GameRng>,
 ) {
-    let tile1 = (
-        PbrBundle {
-            mesh: meshes.add(Mesh::from(shape::Cube { size: 1.0 })),
-            transform: Transform::from_xyz(-15.0, 7.0, 7.0),
-            material: materials.add(Color::RED.into()),
-            ..default()
-        },
-        RigidBody::Fixed,
-        Collider::cuboid(0.5, 0.5, 0.5),
-        Kovaak,
-    );
-
-    let tile2 = (
-        PbrBundle {
-            mesh: meshes.add(Mesh::from(shape::Cube { size: 1.0 })),
-            transform: Transform::from_xyz(-15.0, 3.0, 3.0),
-            material: materials.add(Color::RED.into()),
-            ..default()
-        },
-        RigidBody::Fixed,
-        Collider::cuboid(0.5, 0.5, 0.5),
-        Kovaak,
-    );
-
-    commands.spawn(tile1);
-    commands.spawn(tile2);
+    if events.read().next().is_none() {
+        return;
+    }
+
+    // MapLoaded can come more than once per map (imported scenes add their own spawns)
+    for entity in old_q.iter() {
+        commands.entity(entity).despawn();
+    }
+
+    let mesh = meshes.add(Mesh::from(shape::Cube { size: 1.0 }));
+    let material = materials.add(Color::RED.into());
+
+    for target_volume in spawn_points.targets.iter() {
+        let volume = SpawnVolume {
+            min: target_volume.min.into(),
+            max: target_volume.max.into(),
+        };
+
+        for _ in 0..target_volume.count {
+            let tile = (
+                PbrBundle {
+                    mesh: mesh.clone(),
+                    transform: Transform::from_translation(volume.random_point(&mut rng)),
+                    material: material.clone(),
+                    ..default()
+                },
+                RigidBody::Fixed,
+                Collider::cuboid(0.5, 0.5, 0.5),
+                Kovaak,
+                TargetHealth {
+                    max: target_volume.health.max(1),
+                    left: target_volume.health.max(1),
+                },
+                volume,
+                MapEntity,
+            );
+
+            commands.spawn(tile);
+        }
+    }
 }
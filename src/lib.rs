@@ -0,0 +1,33 @@
+// The game is a library so the client (main.rs) and the dedicated server (bin/server.rs)
+// build from the same modules.
+
+pub mod player;
+// pub mod sphere;
+pub mod arena;
+pub mod cli;
+pub mod client;
+pub mod console;
+pub mod course;
+pub mod crosshair;
+pub mod demo;
+pub mod editor;
+pub mod gltf_map;
+pub mod headless;
+pub mod hitmarker;
+pub mod hud;
+pub mod input;
+pub mod jumbotile;
+pub mod map;
+pub mod movement_sound;
+pub mod net;
+pub mod netsim;
+pub mod pads;
+pub mod prediction;
+pub mod rng;
+pub mod server;
+pub mod settings;
+pub mod share_code;
+pub mod sound;
+pub mod spectator;
+pub mod synth;
+pub mod world;
@@ -1,34 +1,138 @@
 #![windows_subsystem = "windows"]
 
-use bevy::{app::App, math::Vec3, utils::default, DefaultPlugins};
-use bevy_rapier3d::prelude::*;
+use std::time::Duration;
 
-mod player;
-// mod sphere;
-mod crosshair;
-mod jumbotile;
-mod world;
+use bevy::{
+    app::{App, PluginGroup, ScheduleRunnerPlugin},
+    asset::AssetPlugin,
+    log::LogPlugin,
+    math::Vec3,
+    utils::default,
+    window::WindowPlugin,
+    DefaultPlugins, MinimalPlugins,
+};
+use bevy_egui::EguiPlugin;
+use bevy_rapier3d::prelude::*;
 
-use crosshair::CrosshairPlugin;
-use jumbotile::JumboTilePlugin;
-use player::PlayerPlugin;
-// use sphere::SpherePlugin;
-use world::WorldPlugin;
+use bevy_fps_test::{
+    arena::ArenaPlugin,
+    cli::{ExitAfterDemoPlugin, LaunchOptions, LaunchPlugin, Mode},
+    client::NetClientPlugin,
+    console::ConsolePlugin,
+    course::CoursePlugin,
+    crosshair::CrosshairPlugin,
+    demo::{DemoPlugin, DemoUiPlugin},
+    editor::EditorPlugin,
+    gltf_map::GltfMapPlugin,
+    headless::HeadlessPlugin,
+    hitmarker::HitMarkerPlugin,
+    hud::HudPlugin,
+    input::GameInputPlugin,
+    jumbotile::JumboTilePlugin,
+    movement_sound::MovementSoundPlugin,
+    net::TICK_RATE,
+    pads::PadsPlugin,
+    player::{PlayerPlugin, PlayerUiPlugin},
+    rng::RngPlugin,
+    server::dedicated_server,
+    sound::SoundPlugin,
+    spectator::SpectatorPlugin,
+    // sphere::SpherePlugin,
+    world::WorldPlugin,
+};
 
+// see cli.rs for the options, --help lists them
 fn main() {
-    App::new()
-        .add_plugins((
-            DefaultPlugins,
-            RapierPhysicsPlugin::<NoUserData>::default(),
-            // RapierDebugRenderPlugin::default(),
-            PlayerPlugin,
-            WorldPlugin,
-            CrosshairPlugin,
-            JumboTilePlugin,
-        ))
-        .insert_resource(RapierConfiguration {
-            gravity: Vec3::from((0.0, -10.0, 0.0)),
-            ..default()
-        })
-        .run();
+    let options = LaunchOptions::from_args();
+    match options.mode {
+        Mode::Game => game(options).run(),
+        Mode::Headless => headless(options).run(),
+        Mode::Server => dedicated_server(&options).run(),
+    }
+}
+
+fn game(options: LaunchOptions) -> App {
+    let mut app = App::new();
+    app.add_plugins((
+        // watching lets maps hot reload while the game is running
+        DefaultPlugins
+            .set(AssetPlugin {
+                watch_for_changes_override: Some(true),
+                ..default()
+            })
+            .set(WindowPlugin {
+                primary_window: Some(options.window()),
+                ..default()
+            }),
+        EguiPlugin,
+        RapierPhysicsPlugin::<NoUserData>::default(),
+        // RapierDebugRenderPlugin::default(),
+    ))
+    // RngPlugin and NetClientPlugin read these while they're added
+    .insert_resource(options.net)
+    .insert_resource(options)
+    .add_plugins((
+        RngPlugin,
+        SoundPlugin,
+        GameInputPlugin,
+        PlayerPlugin,
+        PlayerUiPlugin,
+        WorldPlugin,
+        GltfMapPlugin,
+        EditorPlugin,
+        ArenaPlugin,
+        CrosshairPlugin,
+        HitMarkerPlugin,
+        HudPlugin,
+        JumboTilePlugin,
+        PadsPlugin,
+        CoursePlugin,
+    ))
+    .add_plugins((
+        DemoPlugin,
+        DemoUiPlugin,
+        MovementSoundPlugin,
+        NetClientPlugin,
+        SpectatorPlugin,
+        LaunchPlugin,
+        ConsolePlugin,
+    ))
+    .insert_resource(RapierConfiguration {
+        gravity: Vec3::from((0.0, -10.0, 0.0)),
+        ..default()
+    });
+    app
+}
+
+// Just the player and the world, for playing demos back from scripts. A demo runs as fast
+// as the machine can go (its frame times are replayed, not waited out) and the game quits
+// after it.
+fn headless(options: LaunchOptions) -> App {
+    let wait = if options.demo.is_some() {
+        Duration::ZERO
+    } else {
+        Duration::from_secs_f64(1.0 / TICK_RATE)
+    };
+    let exit_after_demo = options.demo.is_some();
+
+    let mut app = App::new();
+    app.add_plugins((
+        MinimalPlugins.set(ScheduleRunnerPlugin::run_loop(wait)),
+        LogPlugin::default(),
+        HeadlessPlugin,
+    ))
+    .insert_resource(options)
+    .add_plugins((
+        RngPlugin,
+        WorldPlugin,
+        PlayerPlugin,
+        JumboTilePlugin,
+        PadsPlugin,
+        DemoPlugin,
+        LaunchPlugin,
+    ));
+    if exit_after_demo {
+        app.add_plugins(ExitAfterDemoPlugin);
+    }
+    app
 }
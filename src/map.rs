@@ -0,0 +1,286 @@
+use std::collections::BTreeMap;
+
+use bevy::{
+    asset::{io::Reader, AssetLoader, AsyncReadExt, LoadContext},
+    prelude::*,
+    utils::BoxedFuture,
+};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+// everything a map file (assets/maps/*.map.ron) can describe
+#[derive(Asset, TypePath, Serialize, Deserialize, Clone, Default, Debug, PartialEq)]
+pub struct Map {
+    #[serde(default)]
+    pub name: String,
+    // fixes the target sequence for this map, otherwise the session seed is used
+    #[serde(default)]
+    pub seed: Option<u64>,
+    #[serde(default)]
+    pub materials: BTreeMap<String, MapMaterial>,
+    #[serde(default)]
+    pub geometry: Vec<Geometry>,
+    #[serde(default)]
+    pub scenes: Vec<MapScene>,
+    #[serde(default)]
+    pub lights: Vec<MapLight>,
+    #[serde(default)]
+    pub triggers: Vec<Trigger>,
+    #[serde(default)]
+    pub player_spawns: Vec<PlayerSpawn>,
+    #[serde(default)]
+    pub target_volumes: Vec<TargetVolume>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct MapMaterial {
+    pub color: [f32; 4],
+    #[serde(default = "default_roughness")]
+    pub roughness: f32,
+    #[serde(default)]
+    pub metallic: f32,
+    #[serde(default)]
+    pub emissive: [f32; 3],
+}
+
+impl Default for MapMaterial {
+    fn default() -> Self {
+        Self {
+            color: [1.0, 1.0, 1.0, 1.0],
+            roughness: default_roughness(),
+            metallic: 0.0,
+            emissive: [0.0; 3],
+        }
+    }
+}
+
+impl MapMaterial {
+    pub fn to_standard(&self) -> StandardMaterial {
+        let [r, g, b, a] = self.color;
+        let [er, eg, eb] = self.emissive;
+        StandardMaterial {
+            base_color: Color::rgba(r, g, b, a),
+            perceptual_roughness: self.roughness,
+            metallic: self.metallic,
+            emissive: Color::rgb(er, eg, eb),
+            alpha_mode: if a < 1.0 {
+                AlphaMode::Blend
+            } else {
+                AlphaMode::Opaque
+            },
+            ..default()
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum Shape {
+    Plane { size: f32 },
+    Cuboid { size: [f32; 3] },
+    Cylinder { radius: f32, height: f32 },
+    Sphere { radius: f32 },
+    // wedge rising towards -Z, the tall side is the back face
+    Ramp { size: [f32; 3] },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Geometry {
+    pub shape: Shape,
+    #[serde(default)]
+    pub position: [f32; 3],
+    // euler angles in degrees, applied in YXZ order
+    #[serde(default)]
+    pub rotation: [f32; 3],
+    pub material: String,
+}
+
+impl Geometry {
+    pub fn transform(&self) -> Transform {
+        Transform::from_translation(self.position.into()).with_rotation(euler(self.rotation))
+    }
+}
+
+// a glTF/GLB file placed into the map, colliders, lights and spawns come from its nodes
+// (see gltf_map.rs for the naming conventions and extras)
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct MapScene {
+    pub path: String,
+    #[serde(default)]
+    pub position: [f32; 3],
+    #[serde(default)]
+    pub rotation: [f32; 3],
+    #[serde(default = "unit_scale")]
+    pub scale: f32,
+}
+
+impl MapScene {
+    pub fn transform(&self) -> Transform {
+        Transform::from_translation(self.position.into())
+            .with_rotation(euler(self.rotation))
+            .with_scale(Vec3::splat(self.scale))
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LightKind {
+    Point,
+    Spot,
+    Directional,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct MapLight {
+    pub kind: LightKind,
+    #[serde(default)]
+    pub position: [f32; 3],
+    #[serde(default)]
+    pub rotation: [f32; 3],
+    #[serde(default = "white")]
+    pub color: [f32; 3],
+    pub intensity: f32,
+    #[serde(default = "default_range")]
+    pub range: f32,
+    #[serde(default)]
+    pub shadows: bool,
+}
+
+impl MapLight {
+    pub fn transform(&self) -> Transform {
+        Transform::from_translation(self.position.into()).with_rotation(euler(self.rotation))
+    }
+}
+
+// invisible-ish boxes that do something to the player when they walk into them
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Trigger {
+    pub kind: TriggerKind,
+    #[serde(default)]
+    pub position: [f32; 3],
+    // only yaw matters for speed pads, which push towards -Z
+    #[serde(default)]
+    pub rotation: [f32; 3],
+    pub size: [f32; 3],
+}
+
+impl Trigger {
+    pub fn transform(&self) -> Transform {
+        Transform::from_translation(self.position.into()).with_rotation(euler(self.rotation))
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum TriggerKind {
+    // replaces the player's velocity
+    JumpPad {
+        velocity: [f32; 3],
+    },
+    // adds horizontal speed in the direction the pad faces
+    SpeedPad {
+        speed: f32,
+    },
+    Teleporter {
+        destination: [f32; 3],
+        // look direction after teleporting, same convention as PlayerSpawn, None keeps it
+        #[serde(default)]
+        yaw: Option<f32>,
+        // velocity gets turned along with the view when yaw is set
+        #[serde(default)]
+        keep_velocity: bool,
+    },
+    // time trial volumes, checkpoints have to be touched in order before the finish counts
+    CourseStart,
+    Checkpoint {
+        order: u32,
+    },
+    CourseFinish,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct PlayerSpawn {
+    pub position: [f32; 3],
+    // degrees, 0 looks down +X like the camera in spawn_player
+    #[serde(default)]
+    pub yaw: f32,
+}
+
+impl PlayerSpawn {
+    pub fn rotation(&self) -> Quat {
+        Quat::from_rotation_y((self.yaw - 90.0).to_radians())
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct TargetVolume {
+    pub min: [f32; 3],
+    pub max: [f32; 3],
+    #[serde(default = "one")]
+    pub count: u32,
+    // hits to take a target down, headshots count double
+    #[serde(default = "one")]
+    pub health: u32,
+}
+
+fn euler(degrees: [f32; 3]) -> Quat {
+    let [x, y, z] = degrees;
+    Quat::from_euler(
+        EulerRot::YXZ,
+        y.to_radians(),
+        x.to_radians(),
+        z.to_radians(),
+    )
+}
+
+fn default_roughness() -> f32 {
+    0.5
+}
+
+fn default_range() -> f32 {
+    20.0
+}
+
+fn white() -> [f32; 3] {
+    [1.0, 1.0, 1.0]
+}
+
+fn unit_scale() -> f32 {
+    1.0
+}
+
+fn one() -> u32 {
+    1
+}
+
+#[derive(Default)]
+pub struct MapLoader;
+
+#[derive(Debug, Error)]
+pub enum MapLoaderError {
+    #[error("could not read map file: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("could not parse map file: {0}")]
+    Ron(#[from] ron::error::SpannedError),
+}
+
+impl AssetLoader for MapLoader {
+    type Asset = Map;
+    type Settings = ();
+    type Error = MapLoaderError;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        _settings: &'a (),
+        _load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<Map, MapLoaderError>> {
+        Box::pin(async move {
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes).await?;
+            let map = ron::de::from_bytes::<Map>(&bytes)?;
+            Ok(map)
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["map.ron"]
+    }
+}
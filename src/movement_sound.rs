@@ -0,0 +1,159 @@
+use bevy::{audio::Volume, prelude::*};
+use bevy_rapier3d::prelude::Velocity;
+
+use crate::{
+    player::{Grounded, Player, Speed},
+    sound::{Sound, Sounds},
+};
+
+// Footsteps, jumps, landings and the slide/wind loops, worked out from how the player is
+// moving rather than from the keys, so pads, rockets and demos sound right too.
+pub struct MovementSoundPlugin;
+
+impl Plugin for MovementSoundPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, (start_loops, movement_sounds).chain());
+    }
+}
+
+// meters between footsteps, so they come faster the quicker you go
+const STRIDE: f32 = 2.0;
+// but never closer together than this many seconds
+const MIN_STEP_INTERVAL: f32 = 0.12;
+// slower than this on the ground is standing still
+const MIN_WALK_SPEED: f32 = 1.0;
+// taking off faster than this upwards is a jump rather than walking off a ledge
+const MIN_JUMP_SPEED: f32 = 2.0;
+// landings softer than this are silent, at FULL_LAND_SPEED they're at full volume
+const MIN_LAND_SPEED: f32 = 3.0;
+const FULL_LAND_SPEED: f32 = 20.0;
+// on the ground this much faster than the player can run is sliding on momentum
+const SLIDE_MARGIN: f32 = 2.0;
+// speed above the slide threshold / in the air at which the loops are at full volume
+const FULL_SLIDE_SPEED: f32 = 15.0;
+const MIN_WIND_SPEED: f32 = 8.0;
+const FULL_WIND_SPEED: f32 = 40.0;
+// how quickly the loops fade to their new volume, per second
+const LOOP_FADE: f32 = 8.0;
+
+#[derive(Component)]
+struct MovementLoop {
+    sound: Sound,
+    // 0..1, scaled by the mix
+    level: f32,
+    // the file it was started from, None when generated
+    path: Option<String>,
+}
+
+#[derive(Default)]
+struct MovementState {
+    grounded: bool,
+    // downwards speed on the last airborne frame, the collision has eaten it by the time
+    // we see the player grounded
+    fall_speed: f32,
+    // fraction of a stride walked since the last footstep
+    stride: f32,
+    since_step: f32,
+}
+
+// Starts the loops silent, they're turned up in movement_sounds, which also follows volume
+// changes. A loop is only started again when its sound file is swapped out.
+fn start_loops(mut commands: Commands, sounds: Sounds, loops: Query<(Entity, &MovementLoop)>) {
+    if !sounds.settings.is_changed() {
+        return;
+    }
+
+    for sound in [Sound::Slide, Sound::Wind] {
+        let path = sounds.settings.path(sound);
+        if let Some((entity, movement_loop)) = loops.iter().find(|(_, l)| l.sound == sound) {
+            if movement_loop.path == path {
+                continue;
+            }
+            commands.entity(entity).despawn();
+        }
+
+        let playback = PlaybackSettings::LOOP.with_volume(Volume::new_relative(0.0));
+        let loop_sound = sounds.play(&mut commands, sound, playback, Vec3::ZERO);
+        commands.entity(loop_sound).insert(MovementLoop {
+            sound,
+            level: 0.0,
+            path,
+        });
+    }
+}
+
+fn movement_sounds(
+    mut commands: Commands,
+    sounds: Sounds,
+    time: Res<Time>,
+    player_q: Query<(&Transform, &Velocity, &Grounded, &Speed), With<Player>>,
+    mut loop_q: Query<(&mut MovementLoop, Option<&AudioSink>)>,
+    mut state: Local<MovementState>,
+) {
+    let Ok((transform, velocity, grounded, speed)) = player_q.get_single() else {
+        return;
+    };
+    let dt = time.delta_seconds();
+    let feet = transform.translation - Vec3::Y * 0.8;
+    let ground_speed = velocity.linvel.xz().length();
+    state.since_step += dt;
+
+    if grounded.0 && !state.grounded && state.fall_speed > MIN_LAND_SPEED {
+        let impact = (state.fall_speed - MIN_LAND_SPEED) / (FULL_LAND_SPEED - MIN_LAND_SPEED);
+        let playback = PlaybackSettings::DESPAWN
+            .with_spatial(true)
+            .with_volume(Volume::new_relative(impact.clamp(0.2, 1.0)));
+        sounds.play(&mut commands, Sound::Land, playback, feet);
+        // the landing counts as a step
+        state.stride = 0.0;
+        state.since_step = 0.0;
+    }
+    if !grounded.0 && state.grounded && velocity.linvel.y > MIN_JUMP_SPEED {
+        sounds.spatial(&mut commands, Sound::Jump, feet);
+    }
+
+    if grounded.0 && ground_speed > MIN_WALK_SPEED {
+        state.stride += ground_speed * dt / STRIDE;
+        if state.stride >= 1.0 && state.since_step >= MIN_STEP_INTERVAL {
+            sounds.spatial(&mut commands, Sound::Footstep, feet);
+            state.stride = 0.0;
+            state.since_step = 0.0;
+        }
+    } else {
+        // first step comes half a stride after starting to walk
+        state.stride = 0.5;
+    }
+
+    state.grounded = grounded.0;
+    state.fall_speed = if grounded.0 {
+        0.0
+    } else {
+        (-velocity.linvel.y).max(0.0)
+    };
+
+    let slide = if grounded.0 {
+        (ground_speed - speed.0 - SLIDE_MARGIN) / FULL_SLIDE_SPEED
+    } else {
+        0.0
+    };
+    let wind = if grounded.0 {
+        0.0
+    } else {
+        (velocity.linvel.length() - MIN_WIND_SPEED) / (FULL_WIND_SPEED - MIN_WIND_SPEED)
+    };
+
+    for (mut movement_loop, sink) in loop_q.iter_mut() {
+        let target = match movement_loop.sound {
+            Sound::Slide => slide,
+            _ => wind,
+        }
+        .clamp(0.0, 1.0);
+        let fade = (LOOP_FADE * dt).min(1.0);
+        movement_loop.level += (target - movement_loop.level) * fade;
+
+        // the sink shows up once bevy has started playing the loop
+        if let Some(sink) = sink {
+            sink.set_volume(movement_loop.level * sounds.settings.volume(movement_loop.sound));
+        }
+    }
+}
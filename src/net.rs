@@ -0,0 +1,387 @@
+use bevy::prelude::*;
+
+use crate::input::InputFrame;
+
+// The wire format shared by client.rs and server.rs. Everything goes over plain UDP, one
+// message per datagram, little endian like the demo files. Nothing is resent: inputs are
+// repeated in the next few packets instead and snapshots always carry the whole state.
+//
+// Handshake: the client sends Hello until it gets Welcome (or Refused) back. After that it
+// streams Input and the server answers every tick with a Snapshot. Bye or TIMEOUT seconds
+// of silence ends it from either side.
+
+pub const DEFAULT_PORT: u16 = 27015;
+// server ticks per second, it sends a snapshot every tick
+pub const TICK_RATE: f64 = 60.0;
+// either side drops the other after this many seconds without a packet
+pub const TIMEOUT: f64 = 5.0;
+// seconds between Hellos while connecting
+pub const HELLO_INTERVAL: f64 = 0.5;
+// how many of its latest inputs the client puts in every packet
+pub const INPUT_REDUNDANCY: usize = 4;
+// big enough for any message we send, the server caps players so snapshots fit
+pub const MAX_PACKET: usize = 1400;
+pub const MAX_PLAYERS: usize = 16;
+pub const MAX_NAME: usize = 16;
+
+// bump when the format changes, the server refuses other versions
+pub const VERSION: u8 = 2;
+const MAGIC: &[u8; 2] = b"BF";
+
+// one frame of a client's buttons, numbered so the server can skip repeats
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct NetInput {
+    pub sequence: u32,
+    pub frame: InputFrame,
+    // yaw and pitch of the camera in radians, see view_rotation
+    pub view: Vec2,
+    // the snapshot tick the other players were drawn at, shots are checked against that
+    pub seen: u32,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ClientMessage {
+    Hello { version: u8, name: String },
+    // oldest first
+    Input(Vec<NetInput>),
+    Bye,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ServerMessage {
+    // the map is an asset path, the client needs the same file
+    Welcome { id: u16, map: String },
+    Refused(String),
+    Snapshot(Snapshot),
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Snapshot {
+    pub tick: u32,
+    // the last input sequence the server has run for the receiving client
+    pub ack: u32,
+    pub players: Vec<PlayerState>,
+    // shots that landed since the last tick
+    pub hits: Vec<HitReport>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct PlayerState {
+    pub id: u16,
+    pub name: String,
+    pub position: Vec3,
+    pub velocity: Vec3,
+    pub view: Vec2,
+    pub health: u8,
+    pub kills: u16,
+    pub deaths: u16,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct HitReport {
+    pub shooter: u16,
+    pub victim: u16,
+    pub point: Vec3,
+    pub headshot: bool,
+    pub kill: bool,
+}
+
+pub fn view_angles(rotation: Quat) -> Vec2 {
+    let (yaw, pitch, _) = rotation.to_euler(EulerRot::YXZ);
+    Vec2::new(yaw, pitch)
+}
+
+// the camera rotation for view angles, built the same way player_input does
+pub fn view_rotation(view: Vec2) -> Quat {
+    Quat::from_axis_angle(Vec3::Y, view.x) * Quat::from_axis_angle(Vec3::X, view.y)
+}
+
+impl ClientMessage {
+    pub fn encode(&self) -> Vec<u8> {
+        let mut out = Writer::new();
+        match self {
+            ClientMessage::Hello { version, name } => {
+                out.u8(0);
+                out.u8(*version);
+                out.string(name);
+            }
+            ClientMessage::Input(inputs) => {
+                out.u8(1);
+                out.u8(inputs.len() as u8);
+                for input in inputs {
+                    out.u32(input.sequence);
+                    out.u16(input.frame.held);
+                    out.u16(input.frame.pressed);
+                    out.vec2(input.frame.look);
+                    out.f32(input.frame.dt);
+                    out.vec2(input.view);
+                    out.u32(input.seen);
+                }
+            }
+            ClientMessage::Bye => out.u8(2),
+        }
+        out.0
+    }
+
+    pub fn decode(bytes: &[u8]) -> Option<Self> {
+        let mut input = Reader::new(bytes)?;
+        let message = match input.u8()? {
+            0 => ClientMessage::Hello {
+                version: input.u8()?,
+                name: input.string()?,
+            },
+            1 => {
+                let count = input.u8()?;
+                let mut inputs = Vec::with_capacity(count as usize);
+                for _ in 0..count {
+                    inputs.push(NetInput {
+                        sequence: input.u32()?,
+                        frame: InputFrame {
+                            held: input.u16()?,
+                            pressed: input.u16()?,
+                            look: input.vec2()?,
+                            dt: input.f32()?,
+                        },
+                        view: input.vec2()?,
+                        seen: input.u32()?,
+                    });
+                }
+                ClientMessage::Input(inputs)
+            }
+            2 => ClientMessage::Bye,
+            _ => return None,
+        };
+        input.finish(message)
+    }
+}
+
+impl ServerMessage {
+    pub fn encode(&self) -> Vec<u8> {
+        let mut out = Writer::new();
+        match self {
+            ServerMessage::Welcome { id, map } => {
+                out.u8(0);
+                out.u16(*id);
+                out.string(map);
+            }
+            ServerMessage::Refused(reason) => {
+                out.u8(1);
+                out.string(reason);
+            }
+            ServerMessage::Snapshot(snapshot) => {
+                out.u8(2);
+                out.u32(snapshot.tick);
+                out.u32(snapshot.ack);
+                out.u8(snapshot.players.len() as u8);
+                for player in &snapshot.players {
+                    out.u16(player.id);
+                    out.string(&player.name);
+                    out.vec3(player.position);
+                    out.vec3(player.velocity);
+                    out.vec2(player.view);
+                    out.u8(player.health);
+                    out.u16(player.kills);
+                    out.u16(player.deaths);
+                }
+                out.u8(snapshot.hits.len() as u8);
+                for hit in &snapshot.hits {
+                    out.u16(hit.shooter);
+                    out.u16(hit.victim);
+                    out.vec3(hit.point);
+                    out.u8(hit.headshot as u8 | (hit.kill as u8) << 1);
+                }
+            }
+        }
+        out.0
+    }
+
+    pub fn decode(bytes: &[u8]) -> Option<Self> {
+        let mut input = Reader::new(bytes)?;
+        let message = match input.u8()? {
+            0 => ServerMessage::Welcome {
+                id: input.u16()?,
+                map: input.string()?,
+            },
+            1 => ServerMessage::Refused(input.string()?),
+            2 => {
+                let tick = input.u32()?;
+                let ack = input.u32()?;
+                let player_count = input.u8()?;
+                let mut players = Vec::with_capacity(player_count as usize);
+                for _ in 0..player_count {
+                    players.push(PlayerState {
+                        id: input.u16()?,
+                        name: input.string()?,
+                        position: input.vec3()?,
+                        velocity: input.vec3()?,
+                        view: input.vec2()?,
+                        health: input.u8()?,
+                        kills: input.u16()?,
+                        deaths: input.u16()?,
+                    });
+                }
+                let hit_count = input.u8()?;
+                let mut hits = Vec::with_capacity(hit_count as usize);
+                for _ in 0..hit_count {
+                    let shooter = input.u16()?;
+                    let victim = input.u16()?;
+                    let point = input.vec3()?;
+                    let flags = input.u8()?;
+                    hits.push(HitReport {
+                        shooter,
+                        victim,
+                        point,
+                        headshot: flags & 1 != 0,
+                        kill: flags & 2 != 0,
+                    });
+                }
+                ServerMessage::Snapshot(Snapshot {
+                    tick,
+                    ack,
+                    players,
+                    hits,
+                })
+            }
+            _ => return None,
+        };
+        input.finish(message)
+    }
+}
+
+struct Writer(Vec<u8>);
+
+impl Writer {
+    fn new() -> Self {
+        Writer(MAGIC.to_vec())
+    }
+
+    fn u8(&mut self, value: u8) {
+        self.0.push(value);
+    }
+
+    fn u16(&mut self, value: u16) {
+        self.0.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn u32(&mut self, value: u32) {
+        self.0.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn f32(&mut self, value: f32) {
+        self.0.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn vec2(&mut self, value: Vec2) {
+        self.f32(value.x);
+        self.f32(value.y);
+    }
+
+    fn vec3(&mut self, value: Vec3) {
+        self.f32(value.x);
+        self.f32(value.y);
+        self.f32(value.z);
+    }
+
+    // u8 length, anything longer is cut off (on a char boundary)
+    fn string(&mut self, value: &str) {
+        let mut end = value.len().min(u8::MAX as usize);
+        while !value.is_char_boundary(end) {
+            end -= 1;
+        }
+        self.u8(end as u8);
+        self.0.extend_from_slice(&value.as_bytes()[..end]);
+    }
+}
+
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    // None for packets that aren't ours
+    fn new(bytes: &'a [u8]) -> Option<Self> {
+        bytes.strip_prefix(MAGIC).map(Reader)
+    }
+
+    fn take<const N: usize>(&mut self) -> Option<[u8; N]> {
+        let bytes = self.bytes(N)?;
+        bytes.try_into().ok()
+    }
+
+    fn bytes(&mut self, len: usize) -> Option<&'a [u8]> {
+        if self.0.len() < len {
+            return None;
+        }
+        let (bytes, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Some(bytes)
+    }
+
+    fn u8(&mut self) -> Option<u8> {
+        Some(self.take::<1>()?[0])
+    }
+
+    fn u16(&mut self) -> Option<u16> {
+        Some(u16::from_le_bytes(self.take()?))
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        Some(u32::from_le_bytes(self.take()?))
+    }
+
+    // we never send NaN or infinity, and they'd panic the Durations built from input times
+    fn f32(&mut self) -> Option<f32> {
+        Some(f32::from_le_bytes(self.take()?)).filter(|value| value.is_finite())
+    }
+
+    fn vec2(&mut self) -> Option<Vec2> {
+        Some(Vec2::new(self.f32()?, self.f32()?))
+    }
+
+    fn vec3(&mut self) -> Option<Vec3> {
+        Some(Vec3::new(self.f32()?, self.f32()?, self.f32()?))
+    }
+
+    fn string(&mut self) -> Option<String> {
+        let len = self.u8()? as usize;
+        String::from_utf8(self.bytes(len)?.to_vec()).ok()
+    }
+
+    // trailing bytes mean it wasn't what we thought it was
+    fn finish<T>(self, message: T) -> Option<T> {
+        self.0.is_empty().then_some(message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn input(dt: f32, view: Vec2) -> ClientMessage {
+        ClientMessage::Input(vec![NetInput {
+            sequence: 7,
+            frame: InputFrame {
+                held: 1,
+                pressed: 1,
+                look: Vec2::new(2.0, -1.0),
+                dt,
+            },
+            view,
+            seen: 3,
+        }])
+    }
+
+    #[test]
+    fn input_round_trip() {
+        let message = input(1.0 / 60.0, Vec2::new(0.5, -0.25));
+        assert_eq!(ClientMessage::decode(&message.encode()), Some(message));
+    }
+
+    #[test]
+    fn non_finite_floats_are_rejected() {
+        for bad in [f32::NAN, f32::INFINITY, f32::NEG_INFINITY] {
+            let bytes = input(bad, Vec2::ZERO).encode();
+            assert_eq!(ClientMessage::decode(&bytes), None, "dt {bad}");
+            let bytes = input(0.01, Vec2::new(0.0, bad)).encode();
+            assert_eq!(ClientMessage::decode(&bytes), None, "view {bad}");
+        }
+    }
+}
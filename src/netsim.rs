@@ -0,0 +1,169 @@
+use std::{
+    io::{self, ErrorKind},
+    net::{SocketAddr, UdpSocket},
+    time::{Duration, Instant},
+};
+
+use bevy::prelude::*;
+
+// Makes a local connection behave like a bad one, so prediction and lag compensation can be
+// tried out on one machine. Client and server both talk through a NetSocket, which is a
+// plain UDP socket while NetConditions are all zero. Otherwise every packet, in and out, is
+// delayed by the latency give or take the jitter, and can get dropped, sent twice or held
+// back until the ones after it have overtaken it.
+//
+// Set with `--latency 50 --jitter 10 --loss 5 --duplicate 1 --reorder 2` (milliseconds and
+// percent, see cli.rs) on either binary, or in the Multiplayer window on the client.
+
+// packets picked for reordering are held back this many extra milliseconds
+const REORDER_DELAY: f32 = 40.0;
+
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq)]
+pub struct NetConditions {
+    // milliseconds added to every packet each way, ping goes up by twice this
+    pub latency: f32,
+    // milliseconds, each packet's delay is off by up to this much either way
+    pub jitter: f32,
+    // percent of packets dropped
+    pub loss: f32,
+    // percent of packets delivered twice
+    pub duplicate: f32,
+    // percent of packets held back past the next ones
+    pub reorder: f32,
+}
+
+impl NetConditions {
+    pub fn is_perfect(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+pub struct NetSocket {
+    socket: UdpSocket,
+    pub conditions: NetConditions,
+    rng: fastrand::Rng,
+    // held back, soonest due first
+    outgoing: Vec<Delayed>,
+    incoming: Vec<Delayed>,
+}
+
+struct Delayed {
+    due: Instant,
+    addr: SocketAddr,
+    bytes: Vec<u8>,
+}
+
+impl NetSocket {
+    // the socket should be non-blocking
+    pub fn new(socket: UdpSocket) -> Self {
+        Self {
+            socket,
+            conditions: NetConditions::default(),
+            rng: fastrand::Rng::new(),
+            outgoing: Vec::new(),
+            incoming: Vec::new(),
+        }
+    }
+
+    // a dropped packet still counts as sent, like on a real network
+    pub fn send_to(&mut self, bytes: &[u8], addr: SocketAddr) -> io::Result<()> {
+        if self.conditions.is_perfect() && self.outgoing.is_empty() {
+            return self.socket.send_to(bytes, addr).map(drop);
+        }
+        let delayed = self.condition(bytes, addr);
+        insert_by_due(&mut self.outgoing, delayed);
+        self.flush()
+    }
+
+    // WouldBlock when nothing is due yet
+    pub fn recv_from(&mut self, buffer: &mut [u8]) -> io::Result<(usize, SocketAddr)> {
+        // sends only go out when we're called, so keep them moving while the other side
+        // is quiet too. A failed send is the sender's problem, not a receive error.
+        let _ = self.flush();
+        if self.conditions.is_perfect() && self.incoming.is_empty() {
+            return self.socket.recv_from(buffer);
+        }
+
+        loop {
+            match self.socket.recv_from(buffer) {
+                Ok((len, addr)) => {
+                    let delayed = self.condition(&buffer[..len], addr);
+                    insert_by_due(&mut self.incoming, delayed);
+                }
+                Err(err) if err.kind() == ErrorKind::WouldBlock => break,
+                Err(err) => return Err(err),
+            }
+        }
+
+        if self
+            .incoming
+            .first()
+            .is_none_or(|packet| packet.due > Instant::now())
+        {
+            return Err(ErrorKind::WouldBlock.into());
+        }
+        let packet = self.incoming.remove(0);
+        let len = packet.bytes.len().min(buffer.len());
+        buffer[..len].copy_from_slice(&packet.bytes[..len]);
+        Ok((len, packet.addr))
+    }
+
+    // what becomes of one packet: nothing, one copy or two, each with its own delay
+    fn condition(&mut self, bytes: &[u8], addr: SocketAddr) -> Vec<Delayed> {
+        if self.roll(self.conditions.loss) {
+            return Vec::new();
+        }
+        let copies = if self.roll(self.conditions.duplicate) {
+            2
+        } else {
+            1
+        };
+
+        let now = Instant::now();
+        (0..copies)
+            .map(|_| {
+                let mut delay =
+                    self.conditions.latency + (self.rng.f32() * 2.0 - 1.0) * self.conditions.jitter;
+                if self.roll(self.conditions.reorder) {
+                    delay += REORDER_DELAY;
+                }
+                Delayed {
+                    due: now + Duration::from_secs_f32(delay.max(0.0) / 1000.0),
+                    addr,
+                    bytes: bytes.to_vec(),
+                }
+            })
+            .collect()
+    }
+
+    fn roll(&mut self, percent: f32) -> bool {
+        self.rng.f32() * 100.0 < percent
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        let now = Instant::now();
+        let due = self
+            .outgoing
+            .iter()
+            .take_while(|packet| packet.due <= now)
+            .count();
+        // one bad packet (say a client that went away) mustn't hold up the rest, the first
+        // error is still passed on
+        let mut result = Ok(());
+        for packet in self.outgoing.drain(..due) {
+            if let Err(err) = self.socket.send_to(&packet.bytes, packet.addr) {
+                if result.is_ok() {
+                    result = Err(err);
+                }
+            }
+        }
+        result
+    }
+}
+
+fn insert_by_due(queue: &mut Vec<Delayed>, packets: Vec<Delayed>) {
+    for packet in packets {
+        let index = queue.partition_point(|queued| queued.due <= packet.due);
+        queue.insert(index, packet);
+    }
+}
@@ -0,0 +1,89 @@
+use bevy::{prelude::*, utils::HashSet};
+use bevy_rapier3d::prelude::*;
+
+use crate::{map::TriggerKind, player::Player, world::MapTrigger};
+
+pub struct PadsPlugin;
+
+impl Plugin for PadsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, use_pads)
+            .add_event::<TriggerEntered>();
+    }
+}
+
+// sent for every trigger the player walks into, pads or not
+#[derive(Event)]
+pub struct TriggerEntered(pub TriggerKind);
+
+// rapier's intersection pairs, pads only fire on the frame a player enters them
+#[allow(clippy::type_complexity)]
+fn use_pads(
+    mut player_q: Query<(Entity, &mut Transform, &mut Velocity), With<Player>>,
+    mut cam_q: Query<(&Parent, &mut Transform), (With<Camera3d>, Without<Player>)>,
+    trigger_q: Query<(Entity, &Transform, &MapTrigger), Without<Player>>,
+    rapier_context: Res<RapierContext>,
+    mut inside: Local<HashSet<(Entity, Entity)>>,
+    mut entered: EventWriter<TriggerEntered>,
+) {
+    for (player_entity, mut player_transform, mut velocity) in player_q.iter_mut() {
+        for (trigger_entity, trigger_transform, MapTrigger(kind)) in trigger_q.iter() {
+            let touching =
+                rapier_context.intersection_pair(player_entity, trigger_entity) == Some(true);
+
+            // keyed by player too, the server has one per client
+            let pair = (player_entity, trigger_entity);
+            if !touching {
+                inside.remove(&pair);
+                continue;
+            }
+            if !inside.insert(pair) {
+                continue;
+            }
+
+            entered.send(TriggerEntered(kind.clone()));
+
+            match kind {
+                TriggerKind::JumpPad { velocity: launch } => {
+                    velocity.linvel = Vec3::from(*launch);
+                }
+                TriggerKind::SpeedPad { speed } => {
+                    let mut push = trigger_transform.forward();
+                    push.y = 0.0;
+                    velocity.linvel += push.normalize_or_zero() * *speed;
+                }
+                TriggerKind::Teleporter {
+                    destination,
+                    yaw,
+                    keep_velocity,
+                } => {
+                    player_transform.translation = Vec3::from(*destination);
+
+                    let mut turn = Quat::IDENTITY;
+                    if let Some(yaw) = yaw {
+                        for (parent, mut cam) in cam_q.iter_mut() {
+                            if parent.get() != player_entity {
+                                continue;
+                            }
+                            let (old_yaw, pitch, _) = cam.rotation.to_euler(EulerRot::YXZ);
+                            let new_yaw = (yaw - 90.0).to_radians();
+                            turn = Quat::from_rotation_y(new_yaw - old_yaw);
+                            cam.rotation = Quat::from_axis_angle(Vec3::Y, new_yaw)
+                                * Quat::from_axis_angle(Vec3::X, pitch);
+                        }
+                    }
+
+                    if *keep_velocity {
+                        velocity.linvel = turn * velocity.linvel;
+                    } else {
+                        velocity.linvel = Vec3::ZERO;
+                    }
+                }
+                // course.rs handles these
+                TriggerKind::CourseStart
+                | TriggerKind::Checkpoint { .. }
+                | TriggerKind::CourseFinish => {}
+            }
+        }
+    }
+}
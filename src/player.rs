@@ -1,93 +1,183 @@
 use std::time::Duration;
 
 use bevy::{
-    audio::{Volume, VolumeLevel},
     core_pipeline::bloom::BloomSettings,
-    input::mouse::MouseMotion,
     prelude::*,
     window::{CursorGrabMode, PrimaryWindow},
 };
 
-use crate::jumbotile::Kovaak;
-use bevy_egui::{egui, EguiContexts, EguiPlugin};
+use crate::editor::{editor_closed, ToggleEditor};
+use crate::input::{
+    InputFrame, BACK, FORWARD, JUMP, LEFT, PAUSE, RELOAD, RIGHT, ROCKET, SHOOT, SPRINT,
+};
+use crate::jumbotile::{spawn_tiles, Kovaak, SpawnVolume, TargetHealth};
+use crate::rng::GameRng;
+use crate::sound::EAR_GAP;
+use crate::world::{MapLoaded, SpawnPoints};
+use bevy_egui::{egui, EguiContexts};
 use bevy_rapier3d::prelude::*;
 
+// Moving, shooting and targets. It needs no window, renderer, audio or egui, so it also
+// runs headless (see headless.rs) with InputFrame filled in by hand. PlayerUiPlugin adds
+// the menu and effects on top, sounds are in sound.rs.
 pub struct PlayerPlugin;
 
 impl Plugin for PlayerPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Startup, spawn_player)
-            .add_plugins(EguiPlugin)
+        app.init_resource::<InputFrame>()
+            .init_resource::<Movement>()
+            .add_systems(Startup, spawn_player)
             .add_systems(
                 Update,
                 (
-                    player_input,
-                    sens_slider,
-                    toggle_bloom,
-                    shot_tar,
+                    player_input.run_if(editor_closed),
+                    // fixed order so demo playback hits the same targets
+                    shot_tar.after(player_input),
                     rocket_jump,
                     despawn_blast,
                     blast_player,
-                    bullet_trail,
+                    move_to_spawn.after(spawn_tiles),
                 ),
             )
-            .add_event::<BloomEvent>()
             .add_event::<ShotTar>()
             .add_event::<RocketJump>()
-            .add_event::<BulletTrail>();
+            .add_event::<BulletTrail>()
+            .add_event::<ShotFired>()
+            .add_event::<TargetHit>();
+    }
+}
+
+// the pause menu, bloom and what explosions look like, needs EguiPlugin
+pub struct PlayerUiPlugin;
+
+impl Plugin for PlayerUiPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (
+                sens_slider.run_if(editor_closed),
+                toggle_bloom,
+                bullet_trail,
+                show_blasts,
+            ),
+        )
+        .add_event::<BloomEvent>();
     }
 }
 
 #[derive(Component)]
-struct Player;
+pub struct Player;
+
+// where the camera and shots start, above the middle of the player's sphere
+pub const EYE_HEIGHT: f32 = 0.5;
+pub const PLAYER_RADIUS: f32 = 0.5;
 
+// the camera in the player's head, spectator.rs and the editor bring their own
 #[derive(Component)]
-struct Paused(bool);
+pub struct PlayerCamera;
 
 #[derive(Component)]
-struct Sensitivity(f32);
+pub struct Paused(pub bool);
 
 #[derive(Component)]
-struct Speed(f32);
+pub struct Sensitivity(pub f32);
+
+// run condition for menus that should only show while paused
+pub fn game_paused(player_q: Query<&Paused, With<Player>>) -> bool {
+    player_q.iter().any(|paused| paused.0)
+}
 
 #[derive(Component)]
-struct RocketCooldown {
-    timer: Timer,
+pub struct Speed(pub f32);
+
+// what walk() moves players with, in m/s, the console can change them
+#[derive(Resource, Clone, Copy)]
+pub struct Movement {
+    pub walk_speed: f32,
+    pub sprint_speed: f32,
+    pub jump_speed: f32,
+}
+
+impl Default for Movement {
+    fn default() -> Self {
+        Self {
+            walk_speed: 5.0,
+            sprint_speed: 30.0,
+            jump_speed: 5.0,
+        }
+    }
+}
+
+// whether the ground check under the player hit something this frame
+#[derive(Component, Default)]
+pub struct Grounded(pub bool);
+
+// sent every time the gun goes off, hit or miss, from where it went off
+#[derive(Event)]
+pub struct ShotFired(pub Vec3);
+
+#[derive(Component)]
+pub struct RocketCooldown {
+    pub timer: Timer,
 }
 
 #[derive(Component)]
-struct ShootCooldown {
-    timer: Timer,
+pub struct ShootCooldown {
+    pub timer: Timer,
+}
+
+// the default never runs out, a magazine that needs reloading is opt-in (sv_magazine)
+#[derive(Component, Default)]
+pub struct Ammo {
+    pub loaded: u32,
+    // None for unlimited ammo
+    pub capacity: Option<u32>,
+    pub reloading: bool,
+    pub reload: Timer,
+}
+
+impl Ammo {
+    pub fn magazine(capacity: u32) -> Self {
+        Self {
+            loaded: capacity,
+            capacity: Some(capacity),
+            reloading: false,
+            reload: Timer::new(Duration::from_millis(1500), TimerMode::Once),
+        }
+    }
+
+    pub fn refill(&mut self) {
+        if let Some(capacity) = self.capacity {
+            self.loaded = capacity;
+        }
+        self.reloading = false;
+    }
+
+    pub fn start_reload(&mut self) {
+        let Some(capacity) = self.capacity else {
+            return;
+        };
+        if !self.reloading && self.loaded < capacity {
+            self.reloading = true;
+            self.reload.reset();
+        }
+    }
 }
 
 #[derive(Event)]
-struct BulletTrail {
+pub struct BulletTrail {
     start_pos: Vec3,
     direction: Vec3,
 }
 
-//hi there
-fn spawn_player(
-    mut commands: Commands,
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<StandardMaterial>>,
-) {
-    let player = (
-        PbrBundle {
-            mesh: meshes.add(Mesh::from(shape::UVSphere {
-                radius: 0.5,
-                ..default()
-            })),
-            material: materials.add(Color::CRIMSON.into()),
-            transform: Transform::from_xyz(0.0, 0.5, 0.0),
-            ..default()
-        },
+// everything that makes a player move and shoot, the server's players use it too
+pub fn player_body() -> impl Bundle {
+    (
         Player,
-        Paused(false),
-        Sensitivity(0.015),
         Speed(2.0),
+        Grounded::default(),
         RigidBody::Dynamic,
-        Collider::ball(0.5),
+        Collider::ball(PLAYER_RADIUS),
         Velocity::default(),
         LockedAxes::ROTATION_LOCKED,
         Ccd::enabled(),
@@ -106,6 +196,29 @@ fn spawn_player(
         ShootCooldown {
             timer: Timer::new(Duration::from_millis(250), TimerMode::Once),
         },
+        Ammo::default(),
+    )
+}
+
+//hi there
+fn spawn_player(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    let player = (
+        PbrBundle {
+            mesh: meshes.add(Mesh::from(shape::UVSphere {
+                radius: 0.5,
+                ..default()
+            })),
+            material: materials.add(Color::CRIMSON.into()),
+            transform: Transform::from_xyz(0.0, 0.5, 0.0),
+            ..default()
+        },
+        Paused(false),
+        Sensitivity(0.015),
+        player_body(),
     );
 
     let _light = (PointLightBundle {
@@ -119,10 +232,6 @@ fn spawn_player(
     },);
 
     let camera = (
-        PerspectiveProjection {
-            fov: 0.7,
-            ..default()
-        },
         Camera3dBundle {
             transform: Transform::from_xyz(0.0, 0.5, 0.0).looking_at(Vec3::X, Vec3::Y),
             camera: Camera {
@@ -132,6 +241,8 @@ fn spawn_player(
             ..default()
         },
         BloomSettings::NATURAL,
+        SpatialListener::new(EAR_GAP),
+        PlayerCamera,
     );
 
     commands
@@ -142,107 +253,138 @@ fn spawn_player(
         });
 }
 
+// puts the player on one of the map's spawn points whenever a map finishes loading,
+// with everything else reset too so demos always start from the same state
+#[allow(clippy::type_complexity)]
+fn move_to_spawn(
+    mut events: EventReader<MapLoaded>,
+    spawn_points: Res<SpawnPoints>,
+    mut rng: ResMut<GameRng>,
+    mut player_q: Query<
+        (
+            &mut Transform,
+            &mut Velocity,
+            &mut Paused,
+            &mut RocketCooldown,
+            &mut ShootCooldown,
+            &mut Ammo,
+        ),
+        With<Player>,
+    >,
+    mut cam_q: Query<&mut Transform, (With<PlayerCamera>, Without<Player>)>,
+) {
+    if events.read().next().is_none() || spawn_points.player.is_empty() {
+        return;
+    }
+
+    let spawn = &spawn_points.player[rng.usize(..spawn_points.player.len())];
+
+    for (
+        mut player_transform,
+        mut velocity,
+        mut paused,
+        mut rocket_cooldown,
+        mut shoot_cooldown,
+        mut ammo,
+    ) in player_q.iter_mut()
+    {
+        player_transform.translation = spawn.position.into();
+        *velocity = Velocity::zero();
+        paused.0 = false;
+        rocket_cooldown.timer.reset();
+        shoot_cooldown.timer.reset();
+        ammo.refill();
+    }
+
+    for mut cam in cam_q.iter_mut() {
+        cam.rotation = spawn.rotation();
+    }
+}
+
 #[derive(Event)]
-struct ShotTar(Entity);
+pub struct ShotTar {
+    entity: Entity,
+    point: Vec3,
+}
+
+// sent for every shot that lands on a target
+#[derive(Event, Clone, Copy)]
+pub struct TargetHit {
+    pub point: Vec3,
+    pub headshot: bool,
+    // the target went down and moved somewhere else
+    pub kill: bool,
+}
 
+// a rocket went off here
 #[derive(Event)]
-struct RocketJump(Vec3);
+pub struct RocketJump(pub Vec3);
 
-fn player_input(
-    keys: Res<Input<KeyCode>>,
-    mouse_buttons: Res<Input<MouseButton>>,
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+pub fn player_input(
+    input: Res<InputFrame>,
+    movement: Res<Movement>,
     time: Res<Time>,
     mut player_q: Query<
         (
             &Transform,
+            &mut Grounded,
             &mut Paused,
             &mut Sensitivity,
             &mut Speed,
             &mut Velocity,
             &mut RocketCooldown,
             &mut ShootCooldown,
+            &mut Ammo,
         ),
         With<Player>,
     >,
     rapier_context: Res<RapierContext>,
-    mut cam_q: Query<&mut Transform, (With<Camera3d>, Without<Player>)>,
+    mut cam_q: Query<&mut Transform, (With<PlayerCamera>, Without<Player>)>,
     mut q_windows: Query<&mut Window, With<PrimaryWindow>>,
-    mut motion_evr: EventReader<MouseMotion>,
     mut shot_tar: EventWriter<ShotTar>,
     mut rocket_jump: EventWriter<RocketJump>,
     mut bullet_trail: EventWriter<BulletTrail>,
-    mut commands: Commands,
-    asset_server: Res<AssetServer>,
+    mut shot_fired: EventWriter<ShotFired>,
 ) {
     for (
         player_transform,
+        mut grounded,
         mut player_paused,
         player_sens,
         mut player_speed,
         mut velocity,
         mut rocket_cooldown,
         mut shoot_cooldown,
+        mut ammo,
     ) in player_q.iter_mut()
     {
-        let mut direction = Vec3::ZERO;
         let mut cam = cam_q.get_single_mut().unwrap();
-        let hit = rapier_context.cast_ray(
-            player_transform.translation - Vec3::new(0.0, 0.6, 0.0),
-            Vec3::new(0.0, -1.0, 0.0),
-            0.2,
-            true,
-            QueryFilter::only_fixed(),
+        grounded.0 = on_ground(&rapier_context, player_transform.translation);
+        walk(
+            &input,
+            &movement,
+            cam.rotation,
+            grounded.0,
+            time.delta_seconds(),
+            &mut player_speed,
+            &mut velocity,
         );
 
-        // forward
-        if keys.pressed(KeyCode::W) {
-            direction.x += cam.forward().x;
-            direction.z += cam.forward().z;
-        }
-
-        // back
-        if keys.pressed(KeyCode::S) {
-            direction.x += cam.back().x;
-            direction.z += cam.back().z;
-        }
-
-        // left
-        if keys.pressed(KeyCode::A) {
-            direction.x += cam.left().x;
-            direction.z += cam.left().z;
-        }
-
-        // right
-        if keys.pressed(KeyCode::D) {
-            direction.x += cam.right().x;
-            direction.z += cam.right().z;
-        }
-
-        // jump
-        if keys.pressed(KeyCode::Space) && hit.is_some() {
-            velocity.linvel.y = 5.0;
-        }
-
-        // sprinting
-        if keys.pressed(KeyCode::ShiftLeft) {
-            player_speed.0 = 30.0;
-        } else {
-            player_speed.0 = 5.0;
-        }
-
         // "pause"
-        if keys.just_pressed(KeyCode::Escape) {
+        if input.just_pressed(PAUSE) {
             player_paused.0 = !player_paused.0;
         }
 
         // shoot
-        shoot_cooldown.timer.tick(time.delta());
-
-        if mouse_buttons.pressed(MouseButton::Left)
-            && !player_paused.0
-            && shoot_cooldown.timer.finished()
-        {
-            if let Some((entity, _distance)) = rapier_context.cast_ray(
+        if fire_gun(
+            &input,
+            time.delta(),
+            player_paused.0,
+            &mut shoot_cooldown,
+            &mut ammo,
+        ) {
+            if let Some((entity, distance)) = rapier_context.cast_ray(
                 player_transform.translation
                     + Vec3 {
                         x: 0.0,
@@ -252,9 +394,14 @@ fn player_input(
                 cam.forward(),
                 500.0,
                 true,
-                QueryFilter::only_fixed(),
+                QueryFilter::only_fixed().exclude_sensors(),
             ) {
-                shot_tar.send(ShotTar(entity));
+                shot_tar.send(ShotTar {
+                    entity,
+                    point: player_transform.translation
+                        + Vec3::new(0.0, 0.5, 0.0)
+                        + cam.forward() * distance,
+                });
                 bullet_trail.send(BulletTrail {
                     direction: cam.forward(),
                     start_pos: player_transform.translation
@@ -265,22 +412,11 @@ fn player_input(
                         },
                 });
             }
-            shoot_cooldown.timer.reset();
-            commands.spawn(AudioBundle {
-                source: asset_server.load("gunshot.ogg"),
-                settings: PlaybackSettings {
-                    volume: Volume::Relative(VolumeLevel::new(0.05)),
-                    ..default()
-                },
-            });
+            shot_fired.send(ShotFired(cam.translation + player_transform.translation));
         }
 
         // rocket jump thing
-        rocket_cooldown.timer.tick(time.delta());
-        if mouse_buttons.pressed(MouseButton::Right)
-            && !player_paused.0
-            && rocket_cooldown.timer.finished()
-        {
+        if fire_rocket(&input, time.delta(), player_paused.0, &mut rocket_cooldown) {
             if let Some((_entity, distance)) = rapier_context.cast_ray_and_get_normal(
                 player_transform.translation
                     + Vec3 {
@@ -291,39 +427,35 @@ fn player_input(
                 cam.forward(),
                 5.0,
                 true,
-                QueryFilter::only_fixed(),
+                QueryFilter::only_fixed().exclude_sensors(),
             ) {
                 let hit_point = distance.point;
                 rocket_jump.send(RocketJump(hit_point));
             }
-            rocket_cooldown.timer.reset();
         }
 
-        // cursor locking
+        // cursor locking, there's no window when running headless
         if player_paused.0 {
-            let mut primary_window = q_windows.single_mut();
-            primary_window.cursor.grab_mode = CursorGrabMode::None;
-            primary_window.cursor.visible = true;
+            if let Ok(mut primary_window) = q_windows.get_single_mut() {
+                primary_window.cursor.grab_mode = CursorGrabMode::None;
+                primary_window.cursor.visible = true;
+            }
         } else {
-            let (mut yaw, mut pitch, _) = cam.rotation.to_euler(EulerRot::YXZ);
-
-            for ev in motion_evr.read() {
-                pitch -= (ev.delta.y * player_sens.0).to_radians();
-                yaw -= (ev.delta.x * player_sens.0).to_radians();
+            if input.look != Vec2::ZERO {
+                let (mut yaw, mut pitch, _) = cam.rotation.to_euler(EulerRot::YXZ);
+                pitch -= (input.look.y * player_sens.0).to_radians();
+                yaw -= (input.look.x * player_sens.0).to_radians();
 
                 cam.rotation =
                     Quat::from_axis_angle(Vec3::Y, yaw) * Quat::from_axis_angle(Vec3::X, pitch);
             }
 
-            let mut primary_window = q_windows.single_mut();
-            primary_window.cursor.grab_mode = CursorGrabMode::Locked;
-            primary_window.cursor.visible = false;
+            if let Ok(mut primary_window) = q_windows.get_single_mut() {
+                primary_window.cursor.grab_mode = CursorGrabMode::Locked;
+                primary_window.cursor.visible = false;
+            }
         }
 
-        let movement = direction.normalize_or_zero() * player_speed.0 * time.delta_seconds();
-        velocity.linvel.x += movement.x * 2.0;
-        velocity.linvel.z += movement.z * 2.0;
-
         // impulse.impulse.x = movement.x * 2.0;
         // impulse.impulse.z = movement.z * 2.0;
 
@@ -333,6 +465,125 @@ fn player_input(
     }
 }
 
+// whether there's solid ground right under a player at `position`
+pub fn on_ground(rapier_context: &RapierContext, position: Vec3) -> bool {
+    rapier_context
+        .cast_ray(
+            position - Vec3::new(0.0, 0.6, 0.0),
+            Vec3::new(0.0, -1.0, 0.0),
+            0.2,
+            true,
+            QueryFilter::only_fixed().exclude_sensors(),
+        )
+        .is_some()
+}
+
+// One frame of running and jumping, looking along `view`. Rapier does the actual moving,
+// this only pushes the velocity around. The server and network prediction use it too so
+// everyone moves the same.
+pub fn walk(
+    input: &InputFrame,
+    movement: &Movement,
+    view: Quat,
+    grounded: bool,
+    dt: f32,
+    speed: &mut Speed,
+    velocity: &mut Velocity,
+) {
+    let mut direction = Vec3::ZERO;
+    let forward = view * Vec3::NEG_Z;
+    let right = view * Vec3::X;
+
+    // forward
+    if input.held(FORWARD) {
+        direction.x += forward.x;
+        direction.z += forward.z;
+    }
+
+    // back
+    if input.held(BACK) {
+        direction.x -= forward.x;
+        direction.z -= forward.z;
+    }
+
+    // left
+    if input.held(LEFT) {
+        direction.x -= right.x;
+        direction.z -= right.z;
+    }
+
+    // right
+    if input.held(RIGHT) {
+        direction.x += right.x;
+        direction.z += right.z;
+    }
+
+    // jump
+    if input.held(JUMP) && grounded {
+        velocity.linvel.y = movement.jump_speed;
+    }
+
+    // sprinting
+    if input.held(SPRINT) {
+        speed.0 = movement.sprint_speed;
+    } else {
+        speed.0 = movement.walk_speed;
+    }
+
+    let movement = direction.normalize_or_zero() * speed.0 * dt;
+    velocity.linvel.x += movement.x * 2.0;
+    velocity.linvel.z += movement.z * 2.0;
+}
+
+// ticks the gun's cooldown and reload, true if it goes off this frame
+pub fn fire_gun(
+    input: &InputFrame,
+    delta: Duration,
+    paused: bool,
+    cooldown: &mut ShootCooldown,
+    ammo: &mut Ammo,
+) -> bool {
+    cooldown.timer.tick(delta);
+
+    if ammo.reloading && ammo.reload.tick(delta).finished() {
+        ammo.refill();
+    }
+    if input.just_pressed(RELOAD) && !paused {
+        ammo.start_reload();
+    }
+
+    let fire = input.held(SHOOT)
+        && !paused
+        && cooldown.timer.finished()
+        && !ammo.reloading
+        && (ammo.capacity.is_none() || ammo.loaded > 0);
+    if fire {
+        cooldown.timer.reset();
+        if ammo.capacity.is_some() {
+            ammo.loaded -= 1;
+            if ammo.loaded == 0 {
+                ammo.start_reload();
+            }
+        }
+    }
+    fire
+}
+
+// ticks the rocket cooldown, true if one goes off this frame
+pub fn fire_rocket(
+    input: &InputFrame,
+    delta: Duration,
+    paused: bool,
+    cooldown: &mut RocketCooldown,
+) -> bool {
+    cooldown.timer.tick(delta);
+    let fire = input.held(ROCKET) && !paused && cooldown.timer.finished();
+    if fire {
+        cooldown.timer.reset();
+    }
+    fire
+}
+
 fn bullet_trail(
     mut commands: Commands,
     mut meshes: ResMut<Assets<Mesh>>,
@@ -369,25 +620,31 @@ fn bullet_trail(
     }
 }
 
+// the top quarter of a target counts as its head
+pub const HEAD_HEIGHT: f32 = 0.25;
+
 fn shot_tar(
     mut events: EventReader<ShotTar>,
-    mut query: Query<&mut Transform, With<Kovaak>>,
-    asset_server: Res<AssetServer>,
-    mut commands: Commands,
+    mut query: Query<(&mut Transform, &SpawnVolume, &mut TargetHealth), With<Kovaak>>,
+    mut rng: ResMut<GameRng>,
+    mut hits: EventWriter<TargetHit>,
 ) {
-    for ShotTar(entity) in events.read() {
-        if let Ok(mut shot_thing) = query.get_mut(*entity) {
-            shot_thing.translation = Vec3::new(
-                -15.0,
-                fastrand::i32(100..900) as f32 / 100.0,
-                fastrand::i32(100..900) as f32 / 100.0,
-            );
-            commands.spawn(AudioBundle {
-                source: asset_server.load("Hitsound.ogg"),
-                settings: PlaybackSettings {
-                    volume: Volume::Relative(VolumeLevel::new(0.1)),
-                    ..default()
-                },
+    for ShotTar { entity, point } in events.read() {
+        if let Ok((mut shot_thing, volume, mut health)) = query.get_mut(*entity) {
+            // targets are 1 unit cubes
+            let headshot = point.y > shot_thing.translation.y + 0.5 - HEAD_HEIGHT;
+            let damage = if headshot { 2 } else { 1 };
+            health.left = health.left.saturating_sub(damage);
+
+            let kill = health.left == 0;
+            if kill {
+                shot_thing.translation = volume.random_point(&mut rng);
+                health.left = health.max;
+            }
+            hits.send(TargetHit {
+                point: *point,
+                headshot,
+                kill,
             });
         }
 
@@ -405,57 +662,86 @@ fn shot_tar(
     }
 }
 
+const BLAST_RADIUS: f32 = 1.5;
+// m/s² of push per meter between the blast's center and the player
+const BLAST_STRENGTH: f32 = 100.0;
+
 #[derive(Component)]
-struct BlastDuration {
+pub struct BlastDuration {
     timer: Timer,
+    // players it has thrown already
+    pushed: Vec<Entity>,
 }
 
-fn rocket_jump(
-    mut events: EventReader<RocketJump>,
-    mut commands: Commands,
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<StandardMaterial>>,
-) {
+// the invisible part of an explosion, it pushes players away for as long as it lasts
+pub fn blast() -> impl Bundle {
+    (
+        Collider::ball(BLAST_RADIUS),
+        BlastDuration {
+            timer: Timer::new(Duration::from_millis(500), TimerMode::Once),
+            pushed: Vec::new(),
+        },
+        Sensor,
+    )
+}
+
+fn rocket_jump(mut events: EventReader<RocketJump>, mut commands: Commands) {
     for RocketJump(position) in events.read() {
         let explosion = (
-            PbrBundle {
-                mesh: meshes.add(Mesh::from(shape::UVSphere {
-                    radius: 0.5,
-                    ..default()
-                })),
-                material: materials.add(Color::CRIMSON.into()),
-                transform: Transform::from_xyz(position.x, position.y, position.z),
-                ..default()
-            },
-            Collider::ball(1.5),
-            BlastDuration {
-                timer: Timer::new(Duration::from_millis(500), TimerMode::Once),
-            },
-            Sensor,
+            TransformBundle::from_transform(Transform::from_translation(*position)),
+            blast(),
         );
 
         commands.spawn(explosion);
     }
 }
 
-fn blast_player(
-    mut player_q: Query<(&Transform, &mut Velocity, Entity), With<Player>>,
-    blast_q: Query<(&Transform, Entity), With<BlastDuration>>,
-    rapier_context: Res<RapierContext>,
-    time: Res<Time>,
+// a red ball for as long as the blast lasts
+fn show_blasts(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    blast_q: Query<Entity, Added<BlastDuration>>,
 ) {
-    //could do let (player_transform, mut player_velocity) = player_q.get_single_mut().unwrap()
-    for (player_transform, mut player_velocity, player_entity) in player_q.iter_mut() {
-        for (blast_transform, blast_entity) in blast_q.iter() {
-            if rapier_context.intersection_pair(player_entity, blast_entity) == Some(true) {
-                let direction = player_transform.translation - blast_transform.translation;
-                player_velocity.linvel += direction * time.delta_seconds() * 100.0;
+    for entity in blast_q.iter() {
+        commands.entity(entity).insert((
+            meshes.add(Mesh::from(shape::UVSphere {
+                radius: 0.5,
+                ..default()
+            })),
+            materials.add(StandardMaterial::from(Color::CRIMSON)),
+            VisibilityBundle::default(),
+        ));
+    }
+}
+
+// The blast pushes harder the closer the player is to its center. Stepping that push along
+// frame by frame comes out different at every frame rate, so the first frame a player is
+// inside a blast they get all at once the speed they'd have left it with.
+pub fn blast_player(
+    mut player_q: Query<(Entity, &Transform, &mut Velocity), With<Player>>,
+    mut blast_q: Query<(&Transform, &mut BlastDuration)>,
+) {
+    let reach = BLAST_RADIUS + PLAYER_RADIUS;
+    for (player_entity, player_transform, mut player_velocity) in player_q.iter_mut() {
+        for (blast_transform, mut blast) in blast_q.iter_mut() {
+            let offset = player_transform.translation - blast_transform.translation;
+            if offset.length() >= reach || blast.pushed.contains(&player_entity) {
+                continue;
             }
+            blast.pushed.push(player_entity);
+
+            // the push only depends on distance, so what it adds to the outward speed is
+            // the same whichever way the player was going
+            let direction = offset.try_normalize().unwrap_or(Vec3::Y);
+            let speed = player_velocity.linvel.dot(direction);
+            let gained = BLAST_STRENGTH * (reach * reach - offset.length_squared());
+            player_velocity.linvel += direction * ((speed * speed + gained).sqrt() - speed);
         }
     }
 }
 
-fn despawn_blast(
+pub fn despawn_blast(
     mut commands: Commands,
     mut q: Query<(Entity, &mut BlastDuration)>,
     time: Res<Time>,
@@ -477,11 +763,18 @@ struct BloomEvent;
 fn sens_slider(
     mut contexts: EguiContexts,
     mut player_q: Query<(&mut Sensitivity, &Paused), With<Player>>,
-    mut camera_q: Query<&mut PerspectiveProjection>,
+    mut camera_q: Query<&mut Projection, With<PlayerCamera>>,
     mut bloom_e: EventWriter<BloomEvent>,
+    mut editor_e: EventWriter<ToggleEditor>,
 ) {
     for (mut player_sens, paused) in player_q.iter_mut() {
-        for mut camera in camera_q.iter_mut() {
+        for mut projection in camera_q.iter_mut() {
+            let Projection::Perspective(perspective) = projection.as_ref() else {
+                continue;
+            };
+            // same degrees as --fov and cl_fov, and only written back when it changes
+            let before = perspective.fov.to_degrees();
+            let mut fov = before;
             if paused.0 {
                 egui::Window::new("Hello").show(contexts.ctx_mut(), |ui| {
                     ui.label("Sensitiviy");
@@ -490,9 +783,21 @@ fn sens_slider(
                         bloom_e.send(BloomEvent);
                     }
                     ui.label("Fov");
-                    ui.add(egui::DragValue::new(&mut camera.fov).speed(0.05));
+                    ui.add(
+                        egui::DragValue::new(&mut fov)
+                            .speed(0.5)
+                            .clamp_range(1.0..=179.0),
+                    );
+                    if ui.add(egui::Button::new("Level editor")).clicked() {
+                        editor_e.send(ToggleEditor);
+                    }
                 });
             }
+            if fov != before {
+                if let Projection::Perspective(perspective) = projection.as_mut() {
+                    perspective.fov = fov.to_radians();
+                }
+            }
         }
     }
 }
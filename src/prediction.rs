@@ -0,0 +1,180 @@
+use std::collections::VecDeque;
+
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::Velocity;
+
+use crate::{
+    net::{view_rotation, NetInput},
+    player::{walk, Movement, Player, PlayerCamera, Speed, EYE_HEIGHT},
+};
+
+// Client side prediction for networked play. The local player never waits for the server:
+// player_input and rapier move it right away, same as offline, and every input sent is kept
+// here with the state it led to. When a snapshot says where the server ended up after one
+// of those inputs and it's too far from what we had, the inputs the server hasn't run yet
+// are replayed on top of its answer: through walk() again, so jumps and sprinting follow
+// the server's state. Rapier can't step one body on its own, so what physics did on top of
+// walk (gravity, friction, collisions, pads) is reused from the local run.
+//
+// What's left: the server runs all of a tick's inputs and then steps rapier once, while the
+// client steps every frame. Away from TICK_RATE frames per second the two end up a few
+// centimeters apart, mostly under POSITION_TOLERANCE, and anything bigger is corrected
+// rather than piling up.
+//
+// The view doesn't jump with the body, the camera starts out where it was and slides back
+// over a few frames.
+
+// differences smaller than these are left alone, the two simulations never agree exactly
+const POSITION_TOLERANCE: f32 = 0.05;
+const VELOCITY_TOLERANCE: f32 = 0.5;
+// corrections further than this (respawns, missed teleporters) snap the camera too
+const SNAP_DISTANCE: f32 = 3.0;
+// how quickly the camera catches up with a correction, per second
+const SMOOTHING: f32 = 10.0;
+// a few seconds of inputs, anything older than this can't be acked anymore
+const MAX_HISTORY: usize = 512;
+
+#[derive(Resource, Default)]
+pub struct Prediction {
+    history: VecDeque<Predicted>,
+    // camera offset still left from corrections
+    offset: Vec3,
+    // shown in the multiplayer panel
+    pub corrections: u32,
+    pub last_error: f32,
+}
+
+// an input sent to the server and where it left the local player
+struct Predicted {
+    input: NetInput,
+    // what walk was told this frame
+    grounded: bool,
+    position: Vec3,
+    velocity: Vec3,
+}
+
+impl Prediction {
+    pub fn is_empty(&self) -> bool {
+        self.history.is_empty() && self.offset == Vec3::ZERO
+    }
+
+    pub fn clear(&mut self) {
+        *self = default();
+    }
+
+    pub fn record(&mut self, input: NetInput, grounded: bool, position: Vec3, velocity: Vec3) {
+        if self
+            .history
+            .back()
+            .is_some_and(|last| last.input.sequence >= input.sequence)
+        {
+            return;
+        }
+        self.history.push_back(Predicted {
+            input,
+            grounded,
+            position,
+            velocity,
+        });
+        while self.history.len() > MAX_HISTORY {
+            self.history.pop_front();
+        }
+    }
+
+    // the server ran up to `ack` and put us at `position`/`velocity`
+    pub fn reconcile(
+        &mut self,
+        ack: u32,
+        position: Vec3,
+        velocity: Vec3,
+        movement: &Movement,
+        transform: &mut Transform,
+        body: &mut Velocity,
+    ) {
+        while self
+            .history
+            .front()
+            .is_some_and(|oldest| oldest.input.sequence < ack)
+        {
+            self.history.pop_front();
+        }
+        let Some(acked) = self.history.front() else {
+            return;
+        };
+        if acked.input.sequence != ack {
+            return;
+        }
+
+        let position_error = position - acked.position;
+        let velocity_error = velocity - acked.velocity;
+        self.last_error = position_error.length();
+        if position_error.length() < POSITION_TOLERANCE
+            && velocity_error.length() < VELOCITY_TOLERANCE
+        {
+            return;
+        }
+
+        // the local run before each input, for what physics added to it
+        let mut local = (acked.position, acked.velocity);
+        let mut replayed = (position, velocity);
+        for (index, predicted) in self.history.iter_mut().enumerate() {
+            if index > 0 {
+                let dt = predicted.input.frame.dt;
+                let walk_from = |linvel: Vec3| {
+                    let mut velocity = Velocity::linear(linvel);
+                    walk(
+                        &predicted.input.frame,
+                        movement,
+                        view_rotation(predicted.input.view),
+                        predicted.grounded,
+                        dt,
+                        &mut Speed(0.0),
+                        &mut velocity,
+                    );
+                    velocity.linvel
+                };
+                let physics = predicted.velocity - walk_from(local.1);
+                replayed.1 = walk_from(replayed.1) + physics;
+                // the local move, plus however much faster or slower we're going now
+                replayed.0 += predicted.position - local.0 + (replayed.1 - predicted.velocity) * dt;
+            }
+            local = (predicted.position, predicted.velocity);
+            (predicted.position, predicted.velocity) = replayed;
+        }
+
+        // the newest input is the one the body is at right now
+        let correction = replayed.0 - transform.translation;
+        transform.translation = replayed.0;
+        body.linvel = replayed.1;
+        self.corrections += 1;
+
+        self.offset = if correction.length() > SNAP_DISTANCE {
+            Vec3::ZERO
+        } else {
+            self.offset - correction
+        };
+    }
+}
+
+// eases the camera back onto the body after a correction
+pub fn smooth_corrections(
+    mut prediction: ResMut<Prediction>,
+    time: Res<Time>,
+    mut cam_q: Query<&mut Transform, (With<PlayerCamera>, Without<Player>)>,
+    mut applied: Local<Vec3>,
+) {
+    if prediction.offset == Vec3::ZERO && *applied == Vec3::ZERO {
+        return;
+    }
+
+    let mut offset = prediction.offset * (-SMOOTHING * time.delta_seconds()).exp();
+    if offset.length() < 0.001 {
+        offset = Vec3::ZERO;
+    }
+    prediction.offset = offset;
+    *applied = offset;
+
+    for mut cam in cam_q.iter_mut() {
+        cam.translation = Vec3::Y * EYE_HEIGHT + offset;
+    }
+}
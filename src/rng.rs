@@ -0,0 +1,73 @@
+use std::ops::{Deref, DerefMut};
+
+use bevy::prelude::*;
+
+use crate::cli::LaunchOptions;
+
+// reads the seed from LaunchOptions, so that has to be inserted first
+pub struct RngPlugin;
+
+impl Plugin for RngPlugin {
+    fn build(&self, app: &mut App) {
+        let seed = app
+            .world
+            .get_resource::<LaunchOptions>()
+            .and_then(|options| options.seed)
+            .unwrap_or_else(|| fastrand::u64(..));
+        info!("session seed {seed}");
+        app.insert_resource(GameRng::new(seed));
+    }
+}
+
+// Every spawner (targets, player spawns) draws from this. It restarts from the seed each time
+// a map loads, so the same seed and map always give the same target sequence. The session
+// seed comes from `--seed <n>` or is random, a map's own `seed` overrides it.
+#[derive(Resource)]
+pub struct GameRng {
+    session_seed: u64,
+    seed: u64,
+    rng: fastrand::Rng,
+}
+
+impl GameRng {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            session_seed: seed,
+            seed,
+            rng: fastrand::Rng::with_seed(seed),
+        }
+    }
+
+    pub fn session_seed(&self) -> u64 {
+        self.session_seed
+    }
+
+    // the seed the current sequence started from
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    // takes effect on the next restart
+    pub fn set_session_seed(&mut self, seed: u64) {
+        self.session_seed = seed;
+    }
+
+    pub fn restart(&mut self, map_seed: Option<u64>) {
+        self.seed = map_seed.unwrap_or(self.session_seed);
+        self.rng = fastrand::Rng::with_seed(self.seed);
+    }
+}
+
+impl Deref for GameRng {
+    type Target = fastrand::Rng;
+
+    fn deref(&self) -> &Self::Target {
+        &self.rng
+    }
+}
+
+impl DerefMut for GameRng {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.rng
+    }
+}
@@ -0,0 +1,639 @@
+use std::{
+    collections::VecDeque,
+    io::ErrorKind,
+    net::{SocketAddr, UdpSocket},
+    time::Duration,
+};
+
+use bevy::{app::ScheduleRunnerPlugin, log::LogPlugin, prelude::*};
+use bevy_rapier3d::prelude::*;
+
+use crate::{
+    cli::LaunchOptions,
+    headless::HeadlessPlugin,
+    net::{
+        view_rotation, ClientMessage, HitReport, NetInput, PlayerState, ServerMessage, Snapshot,
+        MAX_NAME, MAX_PACKET, MAX_PLAYERS, TICK_RATE, TIMEOUT, VERSION,
+    },
+    netsim::{NetConditions, NetSocket},
+    pads::PadsPlugin,
+    player::{
+        blast, blast_player, despawn_blast, fire_gun, fire_rocket, on_ground, player_body, walk,
+        Ammo, Grounded, Movement, RocketCooldown, ShootCooldown, Speed, EYE_HEIGHT, HEAD_HEIGHT,
+    },
+    rng::{GameRng, RngPlugin},
+    world::{CurrentMap, MapLoaded, SpawnPoints, WorldPlugin},
+};
+
+// The dedicated server's side of deathmatch, dedicated_server() sets up the app around it
+// for bin/server.rs and the game's `--server`.
+// Every client gets a player body like the local one in player.rs, moved by the inputs it
+// sends through the same walk/fire_gun/fire_rocket functions. The server decides what every
+// shot hits and sends everyone the full state each tick.
+//
+// Shots are lag compensated: every other player is put back where they were in the
+// snapshot the shooter was looking at (up to ServerConfig::max_rewind ago) for the ray
+// cast, then moved back. What you hit on your screen is what you hit.
+//
+// Needs ServerConfig and the ListenSocket opened on its port inserted first.
+pub struct ServerPlugin;
+
+impl Plugin for ServerPlugin {
+    fn build(&self, app: &mut App) {
+        let config = app
+            .world
+            .get_resource::<ServerConfig>()
+            .cloned()
+            .unwrap_or_default();
+        let ListenSocket(socket) = app
+            .world
+            .remove_resource::<ListenSocket>()
+            .expect("ServerPlugin needs a ListenSocket, dedicated_server() opens one");
+        info!("listening on port {}, map {}", config.port, config.map);
+
+        let mut socket = NetSocket::new(socket);
+        if let Some(conditions) = app.world.get_resource::<NetConditions>() {
+            if !conditions.is_perfect() {
+                info!("simulating network conditions {conditions:?}");
+            }
+            socket.conditions = *conditions;
+        }
+
+        app.insert_resource(config)
+            .init_resource::<Movement>()
+            .insert_resource(Server {
+                socket,
+                clients: Vec::new(),
+                next_id: 1,
+                tick: 0,
+                hits: Vec::new(),
+                history: VecDeque::new(),
+            })
+            .add_systems(PostStartup, load_server_map)
+            .add_systems(
+                Update,
+                (receive, simulate, blast_player, despawn_blast, respawn_all).chain(),
+            )
+            // after the physics step, so the state acked for an input is the state it led to
+            .add_systems(
+                PostUpdate,
+                (send_snapshots, drop_silent)
+                    .chain()
+                    .after(PhysicsSet::Writeback),
+            );
+    }
+}
+
+// No window, audio or rendering. Maps load as usual but only their own geometry gets
+// colliders, glTF scenes are skipped.
+// exits with an error when the port can't be opened, like bad arguments do in cli.rs
+pub fn dedicated_server(options: &LaunchOptions) -> App {
+    let config = options.server_config();
+    let socket = match ListenSocket::open(config.port) {
+        Ok(socket) => socket,
+        Err(err) => {
+            eprintln!("could not open port {}: {err}", config.port);
+            std::process::exit(1);
+        }
+    };
+
+    let mut app = App::new();
+    app.add_plugins((
+        MinimalPlugins.set(ScheduleRunnerPlugin::run_loop(Duration::from_secs_f64(
+            1.0 / TICK_RATE,
+        ))),
+        LogPlugin::default(),
+        HeadlessPlugin,
+    ))
+    .insert_resource(options.clone())
+    .insert_resource(config)
+    .insert_resource(socket)
+    .insert_resource(options.net)
+    // pads run here too, or they'd disagree with the clients' prediction
+    .add_plugins((RngPlugin, WorldPlugin, PadsPlugin, ServerPlugin));
+    app
+}
+
+// the server's UDP socket, ServerPlugin takes it out of the world when it's added
+#[derive(Resource)]
+pub struct ListenSocket(pub UdpSocket);
+
+impl ListenSocket {
+    pub fn open(port: u16) -> std::io::Result<Self> {
+        let socket = UdpSocket::bind(("0.0.0.0", port))?;
+        socket.set_nonblocking(true)?;
+        Ok(Self(socket))
+    }
+}
+
+#[derive(Resource, Clone)]
+pub struct ServerConfig {
+    pub port: u16,
+    // asset path, clients load the same one
+    pub map: String,
+    // seconds, how far back shots can be rewound, 0 turns lag compensation off
+    pub max_rewind: f32,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            port: crate::net::DEFAULT_PORT,
+            map: crate::world::DEFAULT_MAP.to_string(),
+            max_rewind: 0.25,
+        }
+    }
+}
+
+const MAX_HEALTH: u8 = 100;
+const BODY_DAMAGE: u8 = 25;
+// headshots count double, like on the targets
+const HEAD_DAMAGE: u8 = 50;
+// frame times above this are clamped, so a client can't move further by lying about them
+const MAX_INPUT_DT: f32 = 0.1;
+// inputs queued past this are dropped, oldest first
+const MAX_QUEUED_INPUTS: usize = 120;
+// Seconds of input a client can have run ahead of the server's clock. Every tick adds the
+// time that really passed and every input run spends its dt, so sending extra inputs only
+// fills the queue instead of moving or firing faster. The slack covers packets bunching up.
+const MAX_INPUT_BUDGET: f32 = 0.25;
+
+#[derive(Resource)]
+struct Server {
+    socket: NetSocket,
+    clients: Vec<Client>,
+    next_id: u16,
+    tick: u32,
+    // landed since the last snapshot
+    hits: Vec<HitReport>,
+    // where everyone was in the latest snapshots, oldest first
+    history: VecDeque<Rewind>,
+}
+
+struct Rewind {
+    tick: u32,
+    positions: Vec<(Entity, Vec3)>,
+}
+
+struct Client {
+    addr: SocketAddr,
+    entity: Entity,
+    last_heard: f64,
+}
+
+// a connected player's body on the server
+#[derive(Component)]
+pub struct NetPlayer {
+    pub id: u16,
+    pub name: String,
+    pub view: Vec2,
+    pub health: u8,
+    pub kills: u16,
+    pub deaths: u16,
+    // received but not run yet, oldest first
+    inputs: Vec<NetInput>,
+    // newest input run or queued
+    last_sequence: u32,
+    // newest input run, what snapshots ack
+    last_run: u32,
+    // seconds of input it may still run, see MAX_INPUT_BUDGET
+    budget: f32,
+}
+
+impl Server {
+    fn send(&mut self, addr: SocketAddr, message: &ServerMessage) {
+        if let Err(err) = self.socket.send_to(&message.encode(), addr) {
+            warn!("could not send to {addr}: {err}");
+        }
+    }
+}
+
+fn load_server_map(
+    mut commands: Commands,
+    config: Res<ServerConfig>,
+    asset_server: Res<AssetServer>,
+) {
+    commands.insert_resource(CurrentMap(asset_server.load(config.map.clone())));
+}
+
+fn spawn_position(spawn_points: &SpawnPoints, rng: &mut GameRng) -> Vec3 {
+    if spawn_points.player.is_empty() {
+        return Vec3::new(0.0, 0.5, 0.0);
+    }
+    spawn_points.player[rng.usize(..spawn_points.player.len())]
+        .position
+        .into()
+}
+
+#[allow(clippy::too_many_arguments)]
+fn receive(
+    mut commands: Commands,
+    mut server: ResMut<Server>,
+    mut players: Query<&mut NetPlayer>,
+    config: Res<ServerConfig>,
+    spawn_points: Res<SpawnPoints>,
+    mut rng: ResMut<GameRng>,
+    time: Res<Time>,
+) {
+    let now = time.elapsed_seconds_f64();
+    let mut buffer = [0; MAX_PACKET];
+
+    loop {
+        let (len, addr) = match server.socket.recv_from(&mut buffer) {
+            Ok(received) => received,
+            Err(err) if err.kind() == ErrorKind::WouldBlock => break,
+            // windows reports an earlier send bouncing off a closed port like this
+            Err(err) if err.kind() == ErrorKind::ConnectionReset => continue,
+            Err(err) => {
+                warn!("could not receive: {err}");
+                break;
+            }
+        };
+        let Some(message) = ClientMessage::decode(&buffer[..len]) else {
+            continue;
+        };
+
+        let known = server.clients.iter().position(|client| client.addr == addr);
+        if let Some(index) = known {
+            server.clients[index].last_heard = now;
+        }
+
+        match (message, known) {
+            // the Welcome got lost
+            (ClientMessage::Hello { .. }, Some(index)) => {
+                if let Ok(player) = players.get(server.clients[index].entity) {
+                    let welcome = ServerMessage::Welcome {
+                        id: player.id,
+                        map: config.map.clone(),
+                    };
+                    server.send(addr, &welcome);
+                }
+            }
+            (ClientMessage::Hello { version, name }, None) => {
+                if version != VERSION {
+                    let reason = format!("the server runs protocol version {VERSION}");
+                    server.send(addr, &ServerMessage::Refused(reason));
+                    continue;
+                }
+                if server.clients.len() >= MAX_PLAYERS {
+                    server.send(addr, &ServerMessage::Refused("server is full".to_string()));
+                    continue;
+                }
+
+                let id = server.next_id;
+                server.next_id = server.next_id.wrapping_add(1).max(1);
+                let name: String = name.chars().take(MAX_NAME).collect();
+                info!("{name} joined from {addr}");
+
+                let position = spawn_position(&spawn_points, &mut rng);
+                let entity = commands
+                    .spawn((
+                        TransformBundle::from_transform(Transform::from_translation(position)),
+                        player_body(),
+                        NetPlayer {
+                            id,
+                            name,
+                            view: Vec2::ZERO,
+                            health: MAX_HEALTH,
+                            kills: 0,
+                            deaths: 0,
+                            inputs: Vec::new(),
+                            last_sequence: 0,
+                            last_run: 0,
+                            budget: 0.0,
+                        },
+                    ))
+                    .id();
+                server.clients.push(Client {
+                    addr,
+                    entity,
+                    last_heard: now,
+                });
+                let welcome = ServerMessage::Welcome {
+                    id,
+                    map: config.map.clone(),
+                };
+                server.send(addr, &welcome);
+            }
+            (ClientMessage::Input(inputs), Some(index)) => {
+                let Ok(mut player) = players.get_mut(server.clients[index].entity) else {
+                    continue;
+                };
+                // every packet repeats the last few inputs, only keep the new ones
+                for input in inputs {
+                    if input.sequence > player.last_sequence {
+                        player.last_sequence = input.sequence;
+                        player.inputs.push(input);
+                    }
+                }
+                let excess = player.inputs.len().saturating_sub(MAX_QUEUED_INPUTS);
+                player.inputs.drain(..excess);
+            }
+            (ClientMessage::Bye, Some(index)) => {
+                let client = server.clients.remove(index);
+                commands.entity(client.entity).despawn_recursive();
+                info!("{addr} left");
+            }
+            // strangers only get to say hello
+            (_, None) => {}
+        }
+    }
+}
+
+// a shot to resolve once every player has moved
+struct Shot {
+    shooter: Entity,
+    origin: Vec3,
+    direction: Vec3,
+    // snapshot tick the shooter saw
+    seen: u32,
+}
+
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+fn simulate(
+    mut commands: Commands,
+    mut rapier_context: ResMut<RapierContext>,
+    mut server: ResMut<Server>,
+    config: Res<ServerConfig>,
+    movement: Res<Movement>,
+    mut players: Query<(
+        Entity,
+        &mut Transform,
+        &mut Velocity,
+        &mut NetPlayer,
+        &mut Grounded,
+        &mut Speed,
+        &mut ShootCooldown,
+        &mut RocketCooldown,
+        &mut Ammo,
+    )>,
+    spawn_points: Res<SpawnPoints>,
+    mut rng: ResMut<GameRng>,
+    time: Res<Time>,
+) {
+    let mut shots = Vec::new();
+
+    for (
+        entity,
+        transform,
+        mut velocity,
+        mut player,
+        mut grounded,
+        mut speed,
+        mut shoot_cooldown,
+        mut rocket_cooldown,
+        mut ammo,
+    ) in players.iter_mut()
+    {
+        // rapier only moves the body once per tick, in between inputs it's moved along by
+        // hand so shots and ground checks happen about where they did on the client
+        let mut position = transform.translation;
+
+        // inputs past the budget wait for a later tick
+        let mut budget = (player.budget + time.delta_seconds()).min(MAX_INPUT_BUDGET);
+        let ready = player
+            .inputs
+            .iter()
+            .take_while(|input| {
+                let dt = input_dt(input);
+                let fits = dt <= budget;
+                if fits {
+                    budget -= dt;
+                }
+                fits
+            })
+            .count();
+        player.budget = budget;
+        let inputs: Vec<_> = player.inputs.drain(..ready).collect();
+
+        for input in inputs {
+            let dt = input_dt(&input);
+            let delta = Duration::from_secs_f32(dt);
+            let view = view_rotation(input.view);
+            player.view = input.view;
+            player.last_run = input.sequence;
+            let eye = position + Vec3::Y * EYE_HEIGHT;
+            grounded.0 = on_ground(&rapier_context, position);
+
+            walk(
+                &input.frame,
+                &movement,
+                view,
+                grounded.0,
+                dt,
+                &mut speed,
+                &mut velocity,
+            );
+
+            if fire_gun(&input.frame, delta, false, &mut shoot_cooldown, &mut ammo) {
+                shots.push(Shot {
+                    shooter: entity,
+                    origin: eye,
+                    direction: view * Vec3::NEG_Z,
+                    seen: input.seen,
+                });
+            }
+
+            if fire_rocket(&input.frame, delta, false, &mut rocket_cooldown) {
+                if let Some((_, intersection)) = rapier_context.cast_ray_and_get_normal(
+                    eye,
+                    view * Vec3::NEG_Z,
+                    5.0,
+                    true,
+                    QueryFilter::only_fixed().exclude_sensors(),
+                ) {
+                    commands.spawn((
+                        TransformBundle::from_transform(Transform::from_translation(
+                            intersection.point,
+                        )),
+                        blast(),
+                    ));
+                }
+            }
+
+            position += velocity.linvel * dt;
+        }
+    }
+
+    let oldest = server.tick.saturating_sub(rewind_ticks(&config));
+    for shot in shots {
+        // what the shooter was looking at, but no further back than max_rewind
+        let rewind = server
+            .history
+            .iter()
+            .find(|rewind| rewind.tick == shot.seen.max(oldest));
+        let moved = match rewind {
+            Some(rewind) => move_colliders(&mut rapier_context, &rewind.positions, shot.shooter),
+            None => Vec::new(),
+        };
+        let filter = QueryFilter::new()
+            .exclude_sensors()
+            .exclude_rigid_body(shot.shooter);
+        let hit = rapier_context.cast_ray(shot.origin, shot.direction, 500.0, true, filter);
+        move_colliders(&mut rapier_context, &moved, shot.shooter);
+
+        let Some((entity, distance)) = hit else {
+            continue;
+        };
+        let Ok((_, mut transform, mut velocity, mut victim, .., mut ammo)) =
+            players.get_mut(entity)
+        else {
+            // hit the map
+            continue;
+        };
+
+        let point = shot.origin + shot.direction * distance;
+        // where the victim was when it got hit
+        let center = rewind
+            .and_then(|rewind| rewind.positions.iter().find(|(other, _)| *other == entity))
+            .map_or(transform.translation, |(_, position)| *position);
+        // the body is a radius 0.5 sphere, its top quarter is the head
+        let headshot = point.y > center.y + 0.5 - HEAD_HEIGHT;
+        let damage = if headshot { HEAD_DAMAGE } else { BODY_DAMAGE };
+        victim.health = victim.health.saturating_sub(damage);
+
+        let kill = victim.health == 0;
+        let victim_id = victim.id;
+        if kill {
+            victim.deaths += 1;
+            victim.health = MAX_HEALTH;
+            transform.translation = spawn_position(&spawn_points, &mut rng);
+            *velocity = Velocity::zero();
+            ammo.refill();
+        }
+
+        let Ok((.., mut shooter, _, _, _, _, _)) = players.get_mut(shot.shooter) else {
+            continue;
+        };
+        if kill {
+            shooter.kills += 1;
+        }
+        server.hits.push(HitReport {
+            shooter: shooter.id,
+            victim: victim_id,
+            point,
+            headshot,
+            kill,
+        });
+    }
+}
+
+fn input_dt(input: &NetInput) -> f32 {
+    input.frame.dt.clamp(0.0, MAX_INPUT_DT)
+}
+
+fn rewind_ticks(config: &ServerConfig) -> u32 {
+    (config.max_rewind.max(0.0) as f64 * TICK_RATE).round() as u32
+}
+
+// puts colliders straight into rapier's query pipeline, the bodies and the next physics
+// step never see it. Returns where they were so the same call can put them back.
+fn move_colliders(
+    context: &mut RapierContext,
+    positions: &[(Entity, Vec3)],
+    skip: Entity,
+) -> Vec<(Entity, Vec3)> {
+    let mut previous = Vec::new();
+    for &(entity, position) in positions {
+        if entity == skip {
+            continue;
+        }
+        let Some(&handle) = context.entity2collider().get(&entity) else {
+            // left since
+            continue;
+        };
+        let Some(collider) = context.colliders.get_mut(handle) else {
+            continue;
+        };
+        previous.push((entity, Vec3::from(*collider.translation())));
+        collider.set_translation(position.into());
+    }
+    if !previous.is_empty() {
+        context.update_query_pipeline();
+    }
+    previous
+}
+
+// a new map (or the same one hot reloaded) puts everyone back on a spawn point
+fn respawn_all(
+    mut events: EventReader<MapLoaded>,
+    mut players: Query<(&mut Transform, &mut Velocity, &mut NetPlayer)>,
+    spawn_points: Res<SpawnPoints>,
+    mut rng: ResMut<GameRng>,
+) {
+    if events.read().next().is_none() {
+        return;
+    }
+
+    for (mut transform, mut velocity, mut player) in players.iter_mut() {
+        transform.translation = spawn_position(&spawn_points, &mut rng);
+        *velocity = Velocity::zero();
+        player.health = MAX_HEALTH;
+    }
+}
+
+fn send_snapshots(
+    mut server: ResMut<Server>,
+    config: Res<ServerConfig>,
+    players: Query<(Entity, &Transform, &Velocity, &NetPlayer)>,
+) {
+    server.tick += 1;
+
+    let tick = server.tick;
+    server.history.push_back(Rewind {
+        tick,
+        positions: players
+            .iter()
+            .map(|(entity, transform, ..)| (entity, transform.translation))
+            .collect(),
+    });
+    while server.history.len() > rewind_ticks(&config) as usize + 1 {
+        server.history.pop_front();
+    }
+
+    let mut states: Vec<PlayerState> = players
+        .iter()
+        .map(|(_, transform, velocity, player)| PlayerState {
+            id: player.id,
+            name: player.name.clone(),
+            position: transform.translation,
+            velocity: velocity.linvel,
+            view: player.view,
+            health: player.health,
+            kills: player.kills,
+            deaths: player.deaths,
+        })
+        .collect();
+    states.sort_by_key(|state| state.id);
+
+    let hits = std::mem::take(&mut server.hits);
+    let receivers: Vec<_> = server
+        .clients
+        .iter()
+        .map(|client| (client.addr, client.entity))
+        .collect();
+    for (addr, entity) in receivers {
+        let Ok((.., player)) = players.get(entity) else {
+            continue;
+        };
+        let snapshot = Snapshot {
+            tick,
+            ack: player.last_run,
+            players: states.clone(),
+            hits: hits.clone(),
+        };
+        server.send(addr, &ServerMessage::Snapshot(snapshot));
+    }
+}
+
+fn drop_silent(mut commands: Commands, mut server: ResMut<Server>, time: Res<Time>) {
+    let now = time.elapsed_seconds_f64();
+    server.clients.retain(|client| {
+        let alive = now - client.last_heard < TIMEOUT;
+        if !alive {
+            info!("{} timed out", client.addr);
+            commands.entity(client.entity).despawn_recursive();
+        }
+        alive
+    });
+}
@@ -0,0 +1,23 @@
+use std::fs;
+
+use bevy::prelude::*;
+use serde::{de::DeserializeOwned, Serialize};
+
+// The ron files the settings windows save to, next to the game. A missing or broken file
+// just means the defaults.
+
+pub fn load_settings<T: DeserializeOwned + Default>(file: &str) -> T {
+    fs::read_to_string(file)
+        .ok()
+        .and_then(|text| ron::from_str(&text).ok())
+        .unwrap_or_default()
+}
+
+pub fn save_settings<T: Serialize>(file: &str, settings: &T) {
+    let result = ron::ser::to_string_pretty(settings, default())
+        .map_err(|err| err.to_string())
+        .and_then(|text| fs::write(file, text).map_err(|err| err.to_string()));
+    if let Err(err) = result {
+        warn!("could not save {file}: {err}");
+    }
+}
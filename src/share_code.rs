@@ -0,0 +1,393 @@
+use thiserror::Error;
+
+use crate::crosshair::{CrosshairSettings, CrosshairStyle};
+
+// Crosshair codes from other games. Neither game works in pixels the same way we do, so
+// sizes are converted roughly as they look at 1080p and some options don't carry over
+// (CS2 dynamic styles, Valorant outer lines and error lines).
+
+#[derive(Debug, Error, PartialEq)]
+pub enum ShareCodeError {
+    #[error("not a CS2 or Valorant crosshair code")]
+    UnknownFormat,
+    #[error("the code is cut short or has extra characters")]
+    Malformed,
+    #[error("'{0}' can't be in a CS2 code")]
+    BadCharacter(char),
+    #[error("the code's checksum doesn't match, is it a crosshair code?")]
+    Checksum,
+    #[error("bad value '{value}' for '{key}'")]
+    BadValue { key: String, value: String },
+}
+
+// picks the format from what the code looks like
+pub fn import(code: &str) -> Result<CrosshairSettings, ShareCodeError> {
+    let code = code.trim();
+    if code.starts_with("CSGO-") {
+        from_cs2(code)
+    } else if code.contains(';') {
+        from_valorant(code)
+    } else {
+        Err(ShareCodeError::UnknownFormat)
+    }
+}
+
+const DICTIONARY: &[u8] = b"ABCDEFGHJKLMNOPQRSTUVWXYZabcdefhijkmnopqrstuvwxyz23456789";
+const CS2_CHARS: usize = 25;
+
+// pixels at 1080p per unit of cl_crosshairsize / cl_crosshairthickness
+const CS2_SCALE: f32 = 2.0;
+// cl_crosshairgap 0 still leaves this many pixels around the center
+const CS2_BASE_GAP: f32 = 4.0;
+
+// the five colors cl_crosshaircolor picks from, 5 is custom
+const CS2_COLORS: [[u8; 3]; 5] = [
+    [250, 50, 50],
+    [50, 250, 50],
+    [250, 250, 50],
+    [50, 50, 250],
+    [50, 250, 250],
+];
+
+// The code is an 18 byte big endian number written in base 57, least significant digit
+// first. Byte 0 is a checksum of the rest.
+pub fn from_cs2(code: &str) -> Result<CrosshairSettings, ShareCodeError> {
+    let digits: Vec<char> = code
+        .trim()
+        .strip_prefix("CSGO-")
+        .ok_or(ShareCodeError::Malformed)?
+        .chars()
+        .filter(|c| *c != '-')
+        .collect();
+    if digits.len() != CS2_CHARS {
+        return Err(ShareCodeError::Malformed);
+    }
+
+    let mut bytes = [0u8; 18];
+    for c in digits.into_iter().rev() {
+        let digit = DICTIONARY
+            .iter()
+            .position(|d| *d as char == c)
+            .ok_or(ShareCodeError::BadCharacter(c))?;
+        if !mul_add(&mut bytes, 57, digit as u32) {
+            return Err(ShareCodeError::Malformed);
+        }
+    }
+    if bytes[0] != checksum(&bytes) {
+        return Err(ShareCodeError::Checksum);
+    }
+
+    let gap = bytes[2] as i8 as f32 / 10.0;
+    let outline = bytes[3] as f32 / 2.0;
+    let color_index = (bytes[10] & 7) as usize;
+    let [r, g, b] = CS2_COLORS
+        .get(color_index)
+        .copied()
+        .unwrap_or([bytes[4], bytes[5], bytes[6]]);
+    let alpha_enabled = bytes[13] & 0x40 != 0;
+    let thickness = bytes[12] as f32 / 10.0;
+    let length = ((((bytes[15] & 0x1f) as u16) << 8) | bytes[14] as u16) as f32 / 10.0;
+
+    Ok(CrosshairSettings {
+        style: CrosshairStyle::Generated,
+        top: bytes[13] & 0x80 == 0,
+        bottom: true,
+        left: true,
+        right: true,
+        length: length * CS2_SCALE,
+        thickness: (thickness * CS2_SCALE).max(1.0),
+        gap: gap + CS2_BASE_GAP,
+        outline: bytes[10] & 8 != 0,
+        outline_thickness: outline,
+        dot: bytes[13] & 0x10 != 0,
+        color: [r, g, b].map(|c| c as f32 / 255.0),
+        opacity: if alpha_enabled {
+            bytes[7] as f32 / 255.0
+        } else {
+            1.0
+        },
+        ..Default::default()
+    })
+}
+
+pub fn to_cs2(settings: &CrosshairSettings) -> String {
+    let to_byte = |value: f32| (value * 255.0).round().clamp(0.0, 255.0) as u8;
+    let length = ((settings.length / CS2_SCALE * 10.0)
+        .round()
+        .clamp(0.0, 8191.0)) as u16;
+    let t_style = !settings.top && settings.bottom && settings.left && settings.right;
+
+    let mut bytes = [0u8; 18];
+    bytes[1] = 1;
+    bytes[2] = ((settings.gap - CS2_BASE_GAP) * 10.0)
+        .round()
+        .clamp(-128.0, 127.0) as i8 as u8;
+    bytes[3] = (settings.outline_thickness * 2.0).round().clamp(0.0, 255.0) as u8;
+    bytes[4] = to_byte(settings.color[0]);
+    bytes[5] = to_byte(settings.color[1]);
+    bytes[6] = to_byte(settings.color[2]);
+    bytes[7] = to_byte(settings.opacity);
+    // split distance, fixed gap and the split alphas/ratio at their defaults
+    bytes[8] = 7;
+    bytes[9] = 30;
+    bytes[10] = 5 | if settings.outline { 8 } else { 0 } | (10 << 4);
+    bytes[11] = 5 | (3 << 4);
+    bytes[12] = (settings.thickness / CS2_SCALE * 10.0)
+        .round()
+        .clamp(0.0, 255.0) as u8;
+    // style 4 (classic static) with alpha on
+    bytes[13] =
+        (4 << 1) | 0x40 | if settings.dot { 0x10 } else { 0 } | if t_style { 0x80 } else { 0 };
+    bytes[14] = (length & 0xff) as u8;
+    bytes[15] = (length >> 8) as u8;
+    bytes[0] = checksum(&bytes);
+
+    let digits: String = (0..CS2_CHARS)
+        .map(|_| DICTIONARY[div_rem(&mut bytes, 57) as usize] as char)
+        .collect();
+    let groups: Vec<&str> = (0..CS2_CHARS)
+        .step_by(5)
+        .map(|start| &digits[start..start + 5])
+        .collect();
+    format!("CSGO-{}", groups.join("-"))
+}
+
+fn checksum(bytes: &[u8; 18]) -> u8 {
+    (bytes[1..].iter().map(|b| *b as u32).sum::<u32>() % 256) as u8
+}
+
+// bytes = bytes * mul + add, false if it didn't fit
+fn mul_add(bytes: &mut [u8; 18], mul: u32, add: u32) -> bool {
+    let mut carry = add;
+    for byte in bytes.iter_mut().rev() {
+        let value = *byte as u32 * mul + carry;
+        *byte = value as u8;
+        carry = value >> 8;
+    }
+    carry == 0
+}
+
+// bytes = bytes / div, returns the remainder
+fn div_rem(bytes: &mut [u8; 18], div: u32) -> u32 {
+    let mut rem = 0;
+    for byte in bytes.iter_mut() {
+        let value = (rem << 8) | *byte as u32;
+        *byte = (value / div) as u8;
+        rem = value % div;
+    }
+    rem
+}
+
+// the presets behind Valorant's "c" key, 8 is custom ("u")
+const VALORANT_COLORS: [[u8; 3]; 8] = [
+    [255, 255, 255],
+    [0, 255, 0],
+    [127, 255, 0],
+    [223, 255, 0],
+    [255, 255, 0],
+    [0, 255, 255],
+    [255, 0, 255],
+    [255, 0, 0],
+];
+
+// Valorant's defaults, the code only has the keys that differ from these
+fn valorant_defaults() -> CrosshairSettings {
+    CrosshairSettings {
+        style: CrosshairStyle::Generated,
+        length: 6.0,
+        thickness: 2.0,
+        gap: 3.0,
+        outline: true,
+        outline_thickness: 1.0,
+        dot: false,
+        color: [1.0, 1.0, 1.0],
+        opacity: 0.8,
+        ..Default::default()
+    }
+}
+
+// "0;P;c;5;h;0;0l;4;0o;2" style codes: a version, then key;value pairs, with P/A/S starting
+// the primary/ADS/sniper sections. Only the primary section's inner lines are used.
+pub fn from_valorant(code: &str) -> Result<CrosshairSettings, ShareCodeError> {
+    let mut settings = valorant_defaults();
+    let mut custom_color = None;
+    let mut color_index = 0;
+    let mut inner_lines = true;
+
+    let mut tokens = code.trim().split(';').skip(1);
+    let mut section = "";
+    while let Some(key) = tokens.next() {
+        if matches!(key, "P" | "A" | "S") {
+            section = key;
+            continue;
+        }
+        let value = tokens.next().ok_or(ShareCodeError::Malformed)?;
+        if section != "P" {
+            continue;
+        }
+
+        let bad_value = || ShareCodeError::BadValue {
+            key: key.to_string(),
+            value: value.to_string(),
+        };
+        let number = || value.parse::<f32>().map_err(|_| bad_value());
+        let flag = || Ok::<_, ShareCodeError>(number()? != 0.0);
+
+        match key {
+            "c" => color_index = number()? as usize,
+            "u" => custom_color = Some(parse_hex_color(value).ok_or_else(&bad_value)?),
+            "h" => settings.outline = flag()?,
+            "t" => settings.outline_thickness = number()?,
+            "d" => settings.dot = flag()?,
+            "0b" => inner_lines = flag()?,
+            "0t" => settings.thickness = number()?,
+            "0l" => settings.length = number()?,
+            "0o" => settings.gap = number()?,
+            "0a" => settings.opacity = number()?,
+            _ => {}
+        }
+    }
+
+    let [r, g, b] = match (color_index, custom_color) {
+        (8, Some(custom)) => custom,
+        (index, _) => VALORANT_COLORS.get(index).copied().unwrap_or([255; 3]),
+    };
+    settings.color = [r, g, b].map(|c| c as f32 / 255.0);
+    if !inner_lines {
+        settings.length = 0.0;
+    }
+
+    Ok(settings)
+}
+
+pub fn to_valorant(settings: &CrosshairSettings) -> String {
+    let [r, g, b] = settings
+        .color
+        .map(|c| (c * 255.0).round().clamp(0.0, 255.0) as u8);
+    // valorant can't hide single lines, the outer lines are turned off since we don't have them
+    format!(
+        "0;P;c;8;u;{r:02X}{g:02X}{b:02X}FF;h;{};t;{};d;{};0b;{};0t;{};0l;{};0o;{};0a;{:.3};0f;0;0m;0;1b;0",
+        settings.outline as u8,
+        settings.outline_thickness.round(),
+        settings.dot as u8,
+        (settings.length > 0.0) as u8,
+        settings.thickness.round(),
+        settings.length.round(),
+        settings.gap.round(),
+        settings.opacity,
+    )
+}
+
+// RRGGBB or RRGGBBAA, alpha is ignored
+fn parse_hex_color(hex: &str) -> Option<[u8; 3]> {
+    let channel = |index: usize| u8::from_str_radix(hex.get(index * 2..index * 2 + 2)?, 16).ok();
+    if hex.len() != 6 && hex.len() != 8 {
+        return None;
+    }
+    Some([channel(0)?, channel(1)?, channel(2)?])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(a: f32, b: f32) {
+        assert!((a - b).abs() < 0.01, "{a} != {b}");
+    }
+
+    fn assert_same(a: &CrosshairSettings, b: &CrosshairSettings) {
+        assert_eq!(
+            (a.top, a.bottom, a.left, a.right, a.outline, a.dot),
+            (b.top, b.bottom, b.left, b.right, b.outline, b.dot)
+        );
+        assert_close(a.length, b.length);
+        assert_close(a.thickness, b.thickness);
+        assert_close(a.gap, b.gap);
+        assert_close(a.outline_thickness, b.outline_thickness);
+        assert_close(a.opacity, b.opacity);
+        for (a, b) in a.color.iter().zip(b.color) {
+            assert_close(*a, b);
+        }
+    }
+
+    // sticks to values both formats can hold exactly, like whole pixels
+    fn settings() -> CrosshairSettings {
+        CrosshairSettings {
+            top: false,
+            length: 8.0,
+            thickness: 2.0,
+            gap: 5.0,
+            outline: true,
+            outline_thickness: 1.0,
+            dot: true,
+            color: [1.0, 0.2, 0.6],
+            opacity: 0.6,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn cs2_round_trip() {
+        let code = to_cs2(&settings());
+        assert!(code.starts_with("CSGO-"));
+        assert_same(&import(&code).unwrap(), &settings());
+    }
+
+    #[test]
+    fn valorant_round_trip() {
+        // valorant has no T style
+        let settings = CrosshairSettings {
+            top: true,
+            ..settings()
+        };
+        assert_same(&import(&to_valorant(&settings)).unwrap(), &settings);
+    }
+
+    #[test]
+    fn cs2_known_code() {
+        // size 33, thickness 4.1, gap 1, outline 1.5 but off, custom color 50 250 84 with
+        // alpha off, T style with a dot
+        let settings = import("CSGO-O4Jsi-V36wY-rTMGK-9w7qF-jQ8WB").unwrap();
+        let expected = CrosshairSettings {
+            top: false,
+            length: 33.0 * CS2_SCALE,
+            thickness: 4.1 * CS2_SCALE,
+            gap: 1.0 + CS2_BASE_GAP,
+            outline: false,
+            outline_thickness: 1.5,
+            dot: true,
+            color: [50.0 / 255.0, 250.0 / 255.0, 84.0 / 255.0],
+            opacity: 1.0,
+            ..Default::default()
+        };
+        assert_same(&settings, &expected);
+    }
+
+    #[test]
+    fn valorant_known_code() {
+        // cyan, no outlines, short lines close to the center, other sections ignored
+        let settings = import("0;s;1;P;c;5;h;0;m;1;0l;4;0o;2;0a;1;0f;0;1b;0;S;c;4;o;1").unwrap();
+        let expected = CrosshairSettings {
+            length: 4.0,
+            gap: 2.0,
+            outline: false,
+            color: [0.0, 1.0, 1.0],
+            opacity: 1.0,
+            ..valorant_defaults()
+        };
+        assert_same(&settings, &expected);
+    }
+
+    #[test]
+    fn bad_codes() {
+        assert_eq!(
+            import("CSGO-O4Jsi-V36wY-rTMGK-9w7qF-jQ8WC").unwrap_err(),
+            ShareCodeError::Checksum
+        );
+        assert_eq!(
+            import("CSGO-O4Jsi-V36wY").unwrap_err(),
+            ShareCodeError::Malformed
+        );
+        assert_eq!(import("hello").unwrap_err(), ShareCodeError::UnknownFormat);
+    }
+}
@@ -0,0 +1,351 @@
+use std::collections::BTreeMap;
+
+use bevy::{
+    audio::{AddAudioSource, SpatialScale, Volume, VolumeLevel},
+    ecs::system::SystemParam,
+    prelude::*,
+};
+use bevy_egui::{egui, EguiContexts};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    editor::editor_closed,
+    player::{game_paused, RocketJump, ShotFired},
+    settings::{load_settings, save_settings},
+    synth::Synth,
+};
+
+// Sounds play from where they happen. The listener is on the player camera (see
+// spawn_player), bevy pans between its two ears and falls off with distance squared.
+// Every sound goes through a mixer channel, and users can swap any sound for their own file.
+pub struct SoundPlugin;
+
+impl Plugin for SoundPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_audio_source::<Synth>()
+            .init_resource::<Synths>()
+            .insert_resource(SpatialScale::new(AUDIO_SCALE))
+            .insert_resource(load_settings::<AudioSettings>(SETTINGS_FILE))
+            .add_systems(
+                Update,
+                (
+                    audio_panel.run_if(game_paused).run_if(editor_closed),
+                    stop_tests,
+                    weapon_sounds,
+                ),
+            );
+    }
+}
+
+// world units are shrunk by this before attenuation, so sounds stay loud out to about
+// 1 / AUDIO_SCALE units and fade past that
+pub const AUDIO_SCALE: f32 = 0.1;
+
+// distance between the listener's ears, in world units
+pub const EAR_GAP: f32 = 4.0;
+
+const SETTINGS_FILE: &str = "audio.ron";
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Channel {
+    Weapons,
+    Feedback,
+    Movement,
+    Ambience,
+    Ui,
+}
+
+const CHANNELS: [Channel; 5] = [
+    Channel::Weapons,
+    Channel::Feedback,
+    Channel::Movement,
+    Channel::Ambience,
+    Channel::Ui,
+];
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Sound {
+    Gunshot,
+    Explosion,
+    Hit,
+    Kill,
+    Footstep,
+    Jump,
+    Land,
+    Slide,
+    Wind,
+}
+
+const SOUNDS: [Sound; 9] = [
+    Sound::Gunshot,
+    Sound::Explosion,
+    Sound::Hit,
+    Sound::Kill,
+    Sound::Footstep,
+    Sound::Jump,
+    Sound::Land,
+    Sound::Slide,
+    Sound::Wind,
+];
+
+impl Sound {
+    pub fn channel(self) -> Channel {
+        match self {
+            Sound::Gunshot | Sound::Explosion => Channel::Weapons,
+            Sound::Hit | Sound::Kill => Channel::Feedback,
+            Sound::Footstep | Sound::Jump | Sound::Land | Sound::Slide => Channel::Movement,
+            Sound::Wind => Channel::Ambience,
+        }
+    }
+
+    // None for the sounds generated in synth.rs
+    fn default_path(self) -> Option<&'static str> {
+        match self {
+            // no explosion sound yet, it's the gunshot slowed down (see rocket_jump)
+            Sound::Gunshot | Sound::Explosion => Some("gunshot.ogg"),
+            Sound::Hit | Sound::Kill => Some("Hitsound.ogg"),
+            _ => None,
+        }
+    }
+
+    fn synth(self) -> Option<Synth> {
+        match self {
+            Sound::Footstep => Some(Synth::Footstep),
+            Sound::Jump => Some(Synth::Jump),
+            Sound::Land => Some(Synth::Land),
+            Sound::Slide => Some(Synth::Slide),
+            Sound::Wind => Some(Synth::Wind),
+            _ => None,
+        }
+    }
+
+    // how loud the sound is in the mix before any of the user's volumes
+    fn base_volume(self) -> f32 {
+        match self {
+            Sound::Gunshot => 0.05,
+            Sound::Explosion => 0.15,
+            Sound::Hit | Sound::Kill => 0.1,
+            Sound::Footstep | Sound::Jump => 0.15,
+            Sound::Land => 0.3,
+            Sound::Slide | Sound::Wind => 0.2,
+        }
+    }
+}
+
+// volumes are 0..1 multipliers
+#[derive(Resource, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct AudioSettings {
+    pub master: f32,
+    pub channels: BTreeMap<Channel, f32>,
+    // asset paths replacing the default file of a sound
+    pub overrides: BTreeMap<Sound, String>,
+}
+
+impl Default for AudioSettings {
+    fn default() -> Self {
+        Self {
+            master: 1.0,
+            channels: CHANNELS.into_iter().map(|channel| (channel, 1.0)).collect(),
+            overrides: BTreeMap::new(),
+        }
+    }
+}
+
+impl AudioSettings {
+    pub fn volume(&self, sound: Sound) -> f32 {
+        let channel = self.channels.get(&sound.channel()).copied().unwrap_or(1.0);
+        self.master * channel * sound.base_volume()
+    }
+
+    // None when the sound is generated
+    pub fn path(&self, sound: Sound) -> Option<String> {
+        match self.overrides.get(&sound) {
+            Some(path) if !path.is_empty() => Some(path.clone()),
+            _ => sound.default_path().map(str::to_string),
+        }
+    }
+}
+
+// handles to the generated sounds, made once at startup
+#[derive(Resource)]
+pub struct Synths(BTreeMap<Sound, Handle<Synth>>);
+
+impl FromWorld for Synths {
+    fn from_world(world: &mut World) -> Self {
+        let mut assets = world.resource_mut::<Assets<Synth>>();
+        Synths(
+            SOUNDS
+                .into_iter()
+                .filter_map(|sound| Some((sound, assets.add(sound.synth()?))))
+                .collect(),
+        )
+    }
+}
+
+// everything needed to start a sound from a system
+#[derive(SystemParam)]
+pub struct Sounds<'w> {
+    pub settings: Res<'w, AudioSettings>,
+    asset_server: Res<'w, AssetServer>,
+    synths: Res<'w, Synths>,
+}
+
+impl Sounds<'_> {
+    // a one shot of `sound` at `position` that despawns when it's done
+    pub fn spatial(&self, commands: &mut Commands, sound: Sound, position: Vec3) -> Entity {
+        let playback = PlaybackSettings::DESPAWN.with_spatial(true);
+        self.play(commands, sound, playback, position)
+    }
+
+    pub fn play(
+        &self,
+        commands: &mut Commands,
+        sound: Sound,
+        playback: PlaybackSettings,
+        position: Vec3,
+    ) -> Entity {
+        play_sound(
+            commands,
+            &self.settings,
+            &self.asset_server,
+            &self.synths,
+            sound,
+            playback,
+            position,
+        )
+    }
+}
+
+// playback's volume gets scaled by the mix, position only matters for spatial sounds
+fn play_sound(
+    commands: &mut Commands,
+    settings: &AudioSettings,
+    asset_server: &AssetServer,
+    synths: &Synths,
+    sound: Sound,
+    playback: PlaybackSettings,
+    position: Vec3,
+) -> Entity {
+    let volume = match playback.volume {
+        Volume::Relative(level) | Volume::Absolute(level) => level.get(),
+    };
+    let playback = playback.with_volume(Volume::Relative(VolumeLevel::new(
+        volume * settings.volume(sound),
+    )));
+    let transform = TransformBundle::from_transform(Transform::from_translation(position));
+
+    match (settings.path(sound), synths.0.get(&sound)) {
+        (Some(path), _) => commands.spawn((
+            AudioBundle {
+                source: asset_server.load(path),
+                settings: playback,
+            },
+            transform,
+        )),
+        (None, Some(synth)) => commands.spawn((
+            AudioSourceBundle {
+                source: synth.clone(),
+                settings: playback,
+            },
+            transform,
+        )),
+        (None, None) => commands.spawn(transform),
+    }
+    .id()
+}
+
+// the loops never end on their own, so tests are cut off after this long
+const TEST_SECONDS: f32 = 2.0;
+
+#[derive(Component)]
+struct TestSound(Timer);
+
+fn stop_tests(mut commands: Commands, mut tests: Query<(Entity, &mut TestSound)>, time: Res<Time>) {
+    for (entity, mut test) in tests.iter_mut() {
+        if test.0.tick(time.delta()).finished() {
+            commands.entity(entity).despawn();
+        }
+    }
+}
+
+fn weapon_sounds(
+    mut commands: Commands,
+    sounds: Sounds,
+    mut shots: EventReader<ShotFired>,
+    mut rockets: EventReader<RocketJump>,
+) {
+    for ShotFired(position) in shots.read() {
+        sounds.spatial(&mut commands, Sound::Gunshot, *position);
+    }
+    for RocketJump(position) in rockets.read() {
+        // the default explosion is a gunshot, slowed down it passes for one
+        let mut playback = PlaybackSettings::DESPAWN.with_spatial(true);
+        if !sounds.settings.overrides.contains_key(&Sound::Explosion) {
+            playback = playback.with_speed(0.5);
+        }
+        sounds.play(&mut commands, Sound::Explosion, playback, *position);
+    }
+}
+
+fn audio_panel(
+    mut contexts: EguiContexts,
+    mut settings: ResMut<AudioSettings>,
+    asset_server: Res<AssetServer>,
+    synths: Res<Synths>,
+    mut commands: Commands,
+) {
+    // edit a copy so change detection only fires on real edits
+    let mut edited = settings.clone();
+
+    egui::Window::new("Audio").show(contexts.ctx_mut(), |ui| {
+        ui.add(egui::Slider::new(&mut edited.master, 0.0..=1.0).text("master"));
+        for channel in CHANNELS {
+            let volume = edited.channels.entry(channel).or_insert(1.0);
+            ui.add(
+                egui::Slider::new(volume, 0.0..=1.0).text(format!("{channel:?}").to_lowercase()),
+            );
+        }
+
+        ui.separator();
+        ui.label("Sound files (empty uses the default)");
+        egui::Grid::new("sound overrides").show(ui, |ui| {
+            for sound in SOUNDS {
+                ui.label(format!("{sound:?}"));
+                let path = edited.overrides.entry(sound).or_default();
+                let hint = sound.default_path().unwrap_or("generated");
+                ui.add(egui::TextEdit::singleline(path).hint_text(hint));
+                if ui.button("Test").clicked() {
+                    let test = play_sound(
+                        &mut commands,
+                        &edited,
+                        &asset_server,
+                        &synths,
+                        sound,
+                        PlaybackSettings::DESPAWN,
+                        Vec3::ZERO,
+                    );
+                    commands.entity(test).insert(TestSound(Timer::from_seconds(
+                        TEST_SECONDS,
+                        TimerMode::Once,
+                    )));
+                }
+                ui.end_row();
+            }
+        });
+        edited.overrides.retain(|_, path| !path.is_empty());
+
+        ui.horizontal(|ui| {
+            if ui.button("Save").clicked() {
+                save_settings(SETTINGS_FILE, &edited);
+            }
+            if ui.button("Reset").clicked() {
+                edited = AudioSettings::default();
+            }
+        });
+    });
+
+    if edited != *settings {
+        *settings = edited;
+    }
+}
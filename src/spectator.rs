@@ -0,0 +1,391 @@
+use bevy::{
+    core_pipeline::bloom::BloomSettings,
+    input::mouse::{MouseMotion, MouseWheel},
+    prelude::*,
+};
+use bevy_egui::{egui, EguiContexts};
+use bevy_rapier3d::prelude::*;
+
+use crate::{
+    client::{NetClient, RemotePlayer},
+    console::console_open,
+    demo::run_demo,
+    editor::editor_closed,
+    input::{gather_input, InputFrame, PAUSE},
+    net::view_rotation,
+    player::{game_paused, Paused, Player, PlayerCamera, Sensitivity, EYE_HEIGHT},
+};
+
+// Watching instead of playing, for coaching, building levels and going over demos. The
+// view leaves the player's head for a camera of its own and the keys stop reaching the
+// player, so it stands still unless a demo is driving it. Free fly goes through walls,
+// orbit circles a player and follow looks through their eyes. Orbit and follow watch the
+// local player, or anyone else on the server when playing online.
+//
+// F7 cycles the modes, the Spectator window (while paused) has the rest. WASD/QE fly,
+// scroll changes the fly speed or orbit distance, left/right click switches players.
+pub struct SpectatorPlugin;
+
+impl Plugin for SpectatorPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Spectator>()
+            // a demo still gets to move the player after this
+            .add_systems(
+                PreUpdate,
+                hold_player
+                    .run_if(spectating)
+                    .after(gather_input)
+                    .before(run_demo),
+            )
+            .add_systems(
+                Update,
+                (
+                    // keys typed into the console aren't for us
+                    spectator_keys.run_if(not(console_open)),
+                    spectator_panel.run_if(game_paused),
+                    switch_camera,
+                    pick_target,
+                    steer.run_if(not(console_open)),
+                    watch,
+                )
+                    .chain()
+                    .run_if(editor_closed),
+            );
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum SpectatorMode {
+    // playing normally
+    #[default]
+    Off,
+    FreeFly,
+    Orbit,
+    Follow,
+}
+
+const MODES: [SpectatorMode; 4] = [
+    SpectatorMode::Off,
+    SpectatorMode::FreeFly,
+    SpectatorMode::Orbit,
+    SpectatorMode::Follow,
+];
+
+impl SpectatorMode {
+    fn name(self) -> &'static str {
+        match self {
+            SpectatorMode::Off => "Off",
+            SpectatorMode::FreeFly => "Free fly",
+            SpectatorMode::Orbit => "Orbit",
+            SpectatorMode::Follow => "Follow",
+        }
+    }
+}
+
+#[derive(Resource)]
+pub struct Spectator {
+    pub mode: SpectatorMode,
+    pub fly_speed: f32,
+    pub orbit_distance: f32,
+    // who orbit and follow watch, None for the local player
+    pub target: Option<u16>,
+}
+
+impl Default for Spectator {
+    fn default() -> Self {
+        Self {
+            mode: SpectatorMode::Off,
+            fly_speed: 10.0,
+            orbit_distance: 5.0,
+            target: None,
+        }
+    }
+}
+
+pub fn spectating(spectator: Res<Spectator>) -> bool {
+    spectator.mode != SpectatorMode::Off
+}
+
+#[derive(Component)]
+struct SpectatorCamera {
+    yaw: f32,
+    pitch: f32,
+}
+
+// orbit stays this far in front of walls
+const WALL_GAP: f32 = 0.2;
+
+// the keys fly the camera now, only pause gets through
+fn hold_player(mut input: ResMut<InputFrame>) {
+    *input = InputFrame {
+        pressed: input.pressed & PAUSE,
+        dt: input.dt,
+        ..default()
+    };
+}
+
+fn spectator_keys(keys: Res<Input<KeyCode>>, mut spectator: ResMut<Spectator>) {
+    if keys.just_pressed(KeyCode::F7) {
+        let current = MODES.iter().position(|mode| *mode == spectator.mode);
+        spectator.mode = MODES[current.map_or(0, |index| (index + 1) % MODES.len())];
+    }
+}
+
+// same hand over as the editor: the player's camera is switched off, not moved
+fn switch_camera(
+    mut commands: Commands,
+    spectator: Res<Spectator>,
+    mut player_cam_q: Query<(&mut Camera, &GlobalTransform), With<PlayerCamera>>,
+    spectator_cam_q: Query<Entity, With<SpectatorCamera>>,
+) {
+    let existing = spectator_cam_q.get_single().ok();
+    match (spectator.mode, existing) {
+        (SpectatorMode::Off, Some(entity)) => {
+            commands.entity(entity).despawn_recursive();
+            for (mut camera, _) in player_cam_q.iter_mut() {
+                camera.is_active = true;
+            }
+        }
+        (SpectatorMode::Off, None) | (_, Some(_)) => {}
+        (_, None) => {
+            // start where the player was looking from
+            let mut start = Transform::default();
+            for (mut camera, global_transform) in player_cam_q.iter_mut() {
+                camera.is_active = false;
+                start = global_transform.compute_transform();
+            }
+            let (yaw, pitch, _) = start.rotation.to_euler(EulerRot::YXZ);
+
+            commands.spawn((
+                Camera3dBundle {
+                    transform: start,
+                    camera: Camera {
+                        hdr: true,
+                        ..default()
+                    },
+                    ..default()
+                },
+                BloomSettings::NATURAL,
+                SpectatorCamera { yaw, pitch },
+            ));
+        }
+    }
+}
+
+// the local player first, then everyone else by id
+fn watchable(remote_q: &Query<&RemotePlayer>) -> Vec<Option<u16>> {
+    let mut ids: Vec<u16> = remote_q.iter().map(|remote| remote.id).collect();
+    ids.sort();
+    std::iter::once(None)
+        .chain(ids.into_iter().map(Some))
+        .collect()
+}
+
+// left click watches the next player, right click the one before
+fn pick_target(
+    mouse_buttons: Res<Input<MouseButton>>,
+    mut spectator: ResMut<Spectator>,
+    remote_q: Query<&RemotePlayer>,
+    player_q: Query<&Paused, With<Player>>,
+) {
+    let targets = watchable(&remote_q);
+    let Some(current) = targets.iter().position(|id| *id == spectator.target) else {
+        // they left
+        spectator.target = None;
+        return;
+    };
+    if !matches!(spectator.mode, SpectatorMode::Orbit | SpectatorMode::Follow)
+        || player_q.iter().any(|paused| paused.0)
+    {
+        return;
+    }
+
+    let step = if mouse_buttons.just_pressed(MouseButton::Left) {
+        1
+    } else if mouse_buttons.just_pressed(MouseButton::Right) {
+        targets.len() - 1
+    } else {
+        return;
+    };
+    spectator.target = targets[(current + step) % targets.len()];
+}
+
+// the mouse turns the camera (or swings it around the target), free fly also moves it
+fn steer(
+    keys: Res<Input<KeyCode>>,
+    mut motion_evr: EventReader<MouseMotion>,
+    mut wheel_evr: EventReader<MouseWheel>,
+    time: Res<Time>,
+    mut spectator: ResMut<Spectator>,
+    player_q: Query<(&Paused, &Sensitivity), With<Player>>,
+    mut cam_q: Query<(&mut Transform, &mut SpectatorCamera)>,
+) {
+    let Ok((mut transform, mut cam)) = cam_q.get_single_mut() else {
+        motion_evr.clear();
+        wheel_evr.clear();
+        return;
+    };
+    // the menus are up, the mouse is theirs
+    let Some((_, sensitivity)) = player_q.iter().find(|(paused, _)| !paused.0) else {
+        motion_evr.clear();
+        wheel_evr.clear();
+        return;
+    };
+
+    for ev in motion_evr.read() {
+        cam.yaw -= (ev.delta.x * sensitivity.0).to_radians();
+        cam.pitch -= (ev.delta.y * sensitivity.0).to_radians();
+    }
+    cam.pitch = cam.pitch.clamp(-1.54, 1.54);
+
+    for ev in wheel_evr.read() {
+        match spectator.mode {
+            SpectatorMode::FreeFly => {
+                spectator.fly_speed = (spectator.fly_speed * (1.0 + ev.y * 0.1)).clamp(1.0, 200.0);
+            }
+            SpectatorMode::Orbit => {
+                spectator.orbit_distance =
+                    (spectator.orbit_distance * (1.0 - ev.y * 0.1)).clamp(1.0, 50.0);
+            }
+            SpectatorMode::Off | SpectatorMode::Follow => {}
+        }
+    }
+
+    if spectator.mode != SpectatorMode::FreeFly {
+        return;
+    }
+    transform.rotation = view_rotation(Vec2::new(cam.yaw, cam.pitch));
+
+    // no collisions, it's a noclip camera
+    let mut direction = Vec3::ZERO;
+    if keys.pressed(KeyCode::W) {
+        direction += transform.forward();
+    }
+    if keys.pressed(KeyCode::S) {
+        direction += transform.back();
+    }
+    if keys.pressed(KeyCode::A) {
+        direction += transform.left();
+    }
+    if keys.pressed(KeyCode::D) {
+        direction += transform.right();
+    }
+    if keys.pressed(KeyCode::E) {
+        direction += Vec3::Y;
+    }
+    if keys.pressed(KeyCode::Q) {
+        direction -= Vec3::Y;
+    }
+
+    let boost = if keys.pressed(KeyCode::ShiftLeft) {
+        3.0
+    } else {
+        1.0
+    };
+    transform.translation +=
+        direction.normalize_or_zero() * spectator.fly_speed * boost * time.delta_seconds();
+}
+
+// puts the camera on the watched player for orbit and follow
+fn watch(
+    spectator: Res<Spectator>,
+    client: Res<NetClient>,
+    player_cam_q: Query<&GlobalTransform, With<PlayerCamera>>,
+    remote_q: Query<(&RemotePlayer, &Transform), Without<SpectatorCamera>>,
+    rapier_context: Res<RapierContext>,
+    mut cam_q: Query<(&mut Transform, &SpectatorCamera)>,
+) {
+    let Ok((mut transform, cam)) = cam_q.get_single_mut() else {
+        return;
+    };
+
+    // where the watched player's eyes are and where they look
+    let view = match spectator.target {
+        None => player_cam_q
+            .get_single()
+            .ok()
+            .map(|eye| (eye.translation(), eye.compute_transform().rotation)),
+        Some(id) => remote_q
+            .iter()
+            .find(|(remote, _)| remote.id == id)
+            .map(|(_, remote)| {
+                let look = client
+                    .snapshot
+                    .players
+                    .iter()
+                    .find(|state| state.id == id)
+                    .map_or(Vec2::ZERO, |state| state.view);
+                (
+                    remote.translation + Vec3::Y * EYE_HEIGHT,
+                    view_rotation(look),
+                )
+            }),
+    };
+    let Some((eye, rotation)) = view else {
+        return;
+    };
+
+    match spectator.mode {
+        SpectatorMode::Orbit => {
+            let back = view_rotation(Vec2::new(cam.yaw, cam.pitch)) * Vec3::Z;
+            // pulled in front of any wall between the camera and the player
+            let distance = rapier_context
+                .cast_ray(
+                    eye,
+                    back,
+                    spectator.orbit_distance,
+                    true,
+                    QueryFilter::only_fixed().exclude_sensors(),
+                )
+                .map_or(spectator.orbit_distance, |(_, hit)| {
+                    (hit - WALL_GAP).max(0.0)
+                });
+            *transform =
+                Transform::from_translation(eye + back * distance).looking_at(eye, Vec3::Y);
+        }
+        SpectatorMode::Follow => {
+            transform.translation = eye;
+            transform.rotation = rotation;
+        }
+        SpectatorMode::Off | SpectatorMode::FreeFly => {}
+    }
+}
+
+fn spectator_panel(
+    mut contexts: EguiContexts,
+    mut spectator: ResMut<Spectator>,
+    client: Res<NetClient>,
+    remote_q: Query<&RemotePlayer>,
+) {
+    let name = |target: Option<u16>| match target {
+        None => "You".to_string(),
+        Some(id) => client
+            .snapshot
+            .players
+            .iter()
+            .find(|state| state.id == id)
+            .map_or_else(|| format!("Player {id}"), |state| state.name.clone()),
+    };
+
+    egui::Window::new("Spectator").show(contexts.ctx_mut(), |ui| {
+        ui.horizontal(|ui| {
+            for mode in MODES {
+                ui.selectable_value(&mut spectator.mode, mode, mode.name());
+            }
+        });
+        ui.add(egui::Slider::new(&mut spectator.fly_speed, 1.0..=200.0).text("fly speed"));
+        ui.add(egui::Slider::new(&mut spectator.orbit_distance, 1.0..=50.0).text("orbit distance"));
+
+        let mut target = spectator.target;
+        egui::ComboBox::from_label("Watching")
+            .selected_text(name(target))
+            .show_ui(ui, |ui| {
+                for id in watchable(&remote_q) {
+                    ui.selectable_value(&mut target, id, name(id));
+                }
+            });
+        spectator.target = target;
+
+        ui.label("F7 switch mode, WASD/QE fly, scroll speed/distance, click switch player");
+    });
+}
@@ -0,0 +1,119 @@
+use std::{f32::consts::TAU, time::Duration};
+
+use bevy::{audio::Source, prelude::*};
+
+// Sounds made from noise and sine waves instead of files, for movement where we don't
+// ship any recordings. They play through bevy's audio like any other source, and the
+// looping ones never end so they can sit under a sink whose volume is turned up and down.
+#[derive(Asset, TypePath, Clone, Copy, Debug, PartialEq)]
+pub enum Synth {
+    // short scuff of filtered noise
+    Footstep,
+    // puff of noise with a rising tone
+    Jump,
+    // low thump, the landing volume does the rest
+    Land,
+    // bright noise, loops
+    Slide,
+    // dull rumbling noise, loops
+    Wind,
+}
+
+const SAMPLE_RATE: u32 = 44_100;
+
+impl Synth {
+    // seconds, None for the looping ones
+    fn length(self) -> Option<f32> {
+        match self {
+            Synth::Footstep => Some(0.08),
+            Synth::Jump => Some(0.15),
+            Synth::Land => Some(0.25),
+            Synth::Slide | Synth::Wind => None,
+        }
+    }
+}
+
+impl Decodable for Synth {
+    type DecoderItem = f32;
+    type Decoder = SynthDecoder;
+
+    fn decoder(&self) -> SynthDecoder {
+        SynthDecoder {
+            synth: *self,
+            index: 0,
+            noise: 0x9e37_79b9,
+            low: 0.0,
+        }
+    }
+}
+
+pub struct SynthDecoder {
+    synth: Synth,
+    index: u32,
+    // xorshift state for the white noise
+    noise: u32,
+    // one pole low pass over the noise
+    low: f32,
+}
+
+impl SynthDecoder {
+    fn white(&mut self) -> f32 {
+        self.noise ^= self.noise << 13;
+        self.noise ^= self.noise >> 17;
+        self.noise ^= self.noise << 5;
+        self.noise as f32 / u32::MAX as f32 * 2.0 - 1.0
+    }
+
+    // smaller cutoffs sound duller
+    fn filtered(&mut self, cutoff: f32) -> f32 {
+        let white = self.white();
+        self.low += (white - self.low) * cutoff;
+        self.low
+    }
+}
+
+impl Iterator for SynthDecoder {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        let t = self.index as f32 / SAMPLE_RATE as f32;
+        if self.synth.length().is_some_and(|length| t >= length) {
+            return None;
+        }
+        self.index = self.index.wrapping_add(1);
+
+        let sample = match self.synth {
+            Synth::Footstep => self.filtered(0.3) * 2.0 * (-t * 50.0).exp(),
+            Synth::Jump => {
+                // 200hz sweeping up to about 800hz
+                let tone = (TAU * (200.0 * t + 2000.0 * t * t)).sin();
+                (tone * 0.4 + self.filtered(0.5)) * (-t * 25.0).exp()
+            }
+            Synth::Land => {
+                let thump = (TAU * 55.0 * t).sin() * (-t * 15.0).exp();
+                thump + self.filtered(0.2) * 2.0 * (-t * 40.0).exp()
+            }
+            Synth::Slide => self.filtered(0.4) * 1.5,
+            Synth::Wind => self.filtered(0.02) * 6.0,
+        };
+        Some(sample.clamp(-1.0, 1.0))
+    }
+}
+
+impl Source for SynthDecoder {
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        1
+    }
+
+    fn sample_rate(&self) -> u32 {
+        SAMPLE_RATE
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.synth.length().map(Duration::from_secs_f32)
+    }
+}
@@ -1,61 +1,345 @@
-use bevy::prelude::*;
+use bevy::{prelude::*, render::render_resource::PrimitiveTopology};
 use bevy_rapier3d::prelude::*;
 
+use crate::map::{
+    LightKind, Map, MapLoader, MapMaterial, PlayerSpawn, Shape, TargetVolume, TriggerKind,
+};
+use crate::rng::GameRng;
+
 pub struct WorldPlugin;
 
 impl Plugin for WorldPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Startup, (spawn_floor, spawn_cube, spawn_light));
+        app.init_asset::<Map>()
+            .init_asset_loader::<MapLoader>()
+            .init_resource::<SpawnPoints>()
+            .init_resource::<EditInPlace>()
+            .add_event::<MapLoaded>()
+            .add_systems(Startup, load_map)
+            .add_systems(Update, spawn_map);
     }
 }
 
-fn spawn_floor(
+pub const DEFAULT_MAP: &str = "maps/arena.map.ron";
+
+#[derive(Resource)]
+pub struct CurrentMap(pub Handle<Map>);
+
+// everything tagged with this gets despawned when the map (re)loads
+#[derive(Component)]
+pub struct MapEntity;
+
+#[derive(Resource, Default)]
+pub struct SpawnPoints {
+    pub player: Vec<PlayerSpawn>,
+    pub targets: Vec<TargetVolume>,
+}
+
+// which part of the map file an entity was built from, the editor uses this for picking
+#[derive(Component, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum MapItem {
+    Geometry(usize),
+    Light(usize),
+    Trigger(usize),
+}
+
+// the sensor volume of a pad or teleporter, pads.rs does the actual work
+#[derive(Component)]
+pub struct MapTrigger(pub TriggerKind);
+
+// root of a glTF scene that still needs colliders generated for it
+#[derive(Component)]
+pub struct ImportedScene;
+
+// sent after the map geometry has been (re)built and SpawnPoints is up to date
+#[derive(Event)]
+pub struct MapLoaded;
+
+// Set by the editor right before it writes its changes into the Map asset. The rebuild that
+// follows keeps targets, imported scenes, SpawnPoints and the rng as they were and doesn't
+// send MapLoaded, so the player isn't put back at a spawn and the session carries on.
+#[derive(Resource, Default)]
+pub struct EditInPlace(pub bool);
+
+fn load_map(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(CurrentMap(asset_server.load(DEFAULT_MAP)));
+}
+
+#[allow(clippy::too_many_arguments)]
+fn spawn_map(
     mut commands: Commands,
+    mut events: EventReader<AssetEvent<Map>>,
+    current: Res<CurrentMap>,
+    maps: Res<Assets<Map>>,
+    old_q: Query<(Entity, Has<MapItem>), With<MapEntity>>,
+    mut edit_in_place: ResMut<EditInPlace>,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<StandardMaterial>>,
+    asset_server: Res<AssetServer>,
+    mut spawn_points: ResMut<SpawnPoints>,
+    mut rng: ResMut<GameRng>,
+    mut map_loaded: EventWriter<MapLoaded>,
 ) {
-    let floor = (
-        PbrBundle {
-            mesh: meshes.add(Mesh::from(shape::Plane::from_size(800.0))),
-            material: materials.add(Color::INDIGO.into()),
-            ..default()
-        },
-        RigidBody::Fixed,
-        Collider::cuboid(400.0, 0.1, 400.0),
-    );
+    let id = current.0.id();
+    // read everything so events don't pile up, then check if any were ours
+    let reloaded = events
+        .read()
+        .filter(|ev| ev.is_loaded_with_dependencies(id) || ev.is_modified(id))
+        .count()
+        > 0;
+
+    if !reloaded && !current.is_changed() {
+        return;
+    }
+
+    let Some(map) = maps.get(id) else {
+        return;
+    };
+    // switching maps is never an edit
+    let in_place = std::mem::take(&mut edit_in_place.0) && !current.is_changed();
+
+    // an edit only replaces what was built from the map file, targets and scenes stay
+    for (entity, from_file) in old_q.iter() {
+        if from_file || !in_place {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
 
-    commands.spawn(floor);
+    build_map(&mut commands, map, &mut meshes, &mut materials);
+
+    if in_place {
+        return;
+    }
+
+    // the editor doesn't touch scenes, so they're only instanced again on a real load
+    spawn_scenes(&mut commands, map, &asset_server);
+
+    spawn_points.player = map.player_spawns.clone();
+    spawn_points.targets = map.target_volumes.clone();
+    // everything MapLoaded spawns draws from a fresh sequence
+    rng.restart(map.seed);
+    map_loaded.send(MapLoaded);
 }
 
-fn spawn_cube(
-    mut commands: Commands,
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<StandardMaterial>>,
+pub fn build_map(
+    commands: &mut Commands,
+    map: &Map,
+    meshes: &mut Assets<Mesh>,
+    materials: &mut Assets<StandardMaterial>,
 ) {
-    let mirror = (
-        PbrBundle {
-            mesh: meshes.add(Mesh::from(shape::Cube { size: 5.0 })),
-            transform: Transform::from_xyz(5.0, 2.5, 5.0),
-            material: materials.add(Color::RED.into()),
-            ..default()
-        },
-        RigidBody::Fixed,
-        Collider::cuboid(2.5, 2.5, 2.5),
-    );
+    let fallback = materials.add(MapMaterial::default().to_standard());
+    let handles: Vec<_> = map
+        .materials
+        .iter()
+        .map(|(name, material)| (name.clone(), materials.add(material.to_standard())))
+        .collect();
+
+    for (index, geometry) in map.geometry.iter().enumerate() {
+        let material = handles
+            .iter()
+            .find(|(name, _)| *name == geometry.material)
+            .map(|(_, handle)| handle.clone())
+            .unwrap_or_else(|| fallback.clone());
+
+        let block = (
+            PbrBundle {
+                mesh: meshes.add(shape_mesh(&geometry.shape)),
+                transform: geometry.transform(),
+                material,
+                ..default()
+            },
+            RigidBody::Fixed,
+            shape_collider(&geometry.shape),
+            MapItem::Geometry(index),
+            MapEntity,
+        );
+
+        commands.spawn(block);
+    }
+
+    for (index, trigger) in map.triggers.iter().enumerate() {
+        let [x, y, z] = trigger.size;
+        let color = match trigger.kind {
+            TriggerKind::JumpPad { .. } => Color::rgba(0.2, 1.0, 0.3, 0.35),
+            TriggerKind::SpeedPad { .. } => Color::rgba(1.0, 0.85, 0.1, 0.35),
+            TriggerKind::Teleporter { .. } => Color::rgba(0.6, 0.2, 1.0, 0.35),
+            TriggerKind::CourseStart => Color::rgba(0.2, 0.6, 1.0, 0.25),
+            TriggerKind::Checkpoint { .. } => Color::rgba(1.0, 1.0, 1.0, 0.2),
+            TriggerKind::CourseFinish => Color::rgba(1.0, 0.2, 0.2, 0.25),
+        };
+
+        let pad = (
+            PbrBundle {
+                mesh: meshes.add(Mesh::from(shape::Box::new(x, y, z))),
+                transform: trigger.transform(),
+                material: materials.add(StandardMaterial {
+                    base_color: color,
+                    alpha_mode: AlphaMode::Blend,
+                    unlit: true,
+                    ..default()
+                }),
+                ..default()
+            },
+            Collider::cuboid(x / 2.0, y / 2.0, z / 2.0),
+            Sensor,
+            MapTrigger(trigger.kind.clone()),
+            MapItem::Trigger(index),
+            MapEntity,
+        );
 
-    commands.spawn(mirror);
+        commands.spawn(pad);
+    }
+
+    for (index, light) in map.lights.iter().enumerate() {
+        let [r, g, b] = light.color;
+        let color = Color::rgb(r, g, b);
+
+        match light.kind {
+            LightKind::Point => {
+                commands.spawn((
+                    PointLightBundle {
+                        transform: light.transform(),
+                        point_light: PointLight {
+                            color,
+                            intensity: light.intensity,
+                            range: light.range,
+                            shadows_enabled: light.shadows,
+                            ..default()
+                        },
+                        ..default()
+                    },
+                    MapItem::Light(index),
+                    MapEntity,
+                ));
+            }
+            LightKind::Spot => {
+                commands.spawn((
+                    SpotLightBundle {
+                        transform: light.transform(),
+                        spot_light: SpotLight {
+                            color,
+                            intensity: light.intensity,
+                            range: light.range,
+                            shadows_enabled: light.shadows,
+                            ..default()
+                        },
+                        ..default()
+                    },
+                    MapItem::Light(index),
+                    MapEntity,
+                ));
+            }
+            LightKind::Directional => {
+                commands.spawn((
+                    DirectionalLightBundle {
+                        transform: light.transform(),
+                        directional_light: DirectionalLight {
+                            color,
+                            illuminance: light.intensity,
+                            shadows_enabled: light.shadows,
+                            ..default()
+                        },
+                        ..default()
+                    },
+                    MapItem::Light(index),
+                    MapEntity,
+                ));
+            }
+        }
+    }
 }
 
-fn spawn_light(mut commands: Commands) {
-    let ceiling_light = PointLightBundle {
-        transform: Transform::from_xyz(0.0, 3.0, 0.0),
-        point_light: PointLight {
-            intensity: 5000.0,
-            shadows_enabled: true,
+fn spawn_scenes(commands: &mut Commands, map: &Map, asset_server: &AssetServer) {
+    for scene in map.scenes.iter() {
+        let imported = (
+            SceneBundle {
+                scene: asset_server.load(format!("{}#Scene0", scene.path)),
+                transform: scene.transform(),
+                ..default()
+            },
+            ImportedScene,
+            MapEntity,
+        );
+
+        commands.spawn(imported);
+    }
+}
+
+pub fn shape_mesh(shape: &Shape) -> Mesh {
+    match *shape {
+        Shape::Plane { size } => Mesh::from(shape::Plane::from_size(size)),
+        Shape::Cuboid { size: [x, y, z] } => Mesh::from(shape::Box::new(x, y, z)),
+        Shape::Cylinder { radius, height } => Mesh::from(shape::Cylinder {
+            radius,
+            height,
             ..default()
-        },
-        ..default()
-    };
+        }),
+        Shape::Sphere { radius } => Mesh::from(shape::UVSphere {
+            radius,
+            ..default()
+        }),
+        Shape::Ramp { size } => ramp_mesh(size),
+    }
+}
+
+pub fn shape_collider(shape: &Shape) -> Collider {
+    match *shape {
+        // planes get a thin slab so fast stuff doesn't tunnel through
+        Shape::Plane { size } => Collider::cuboid(size / 2.0, 0.1, size / 2.0),
+        Shape::Cuboid { size: [x, y, z] } => Collider::cuboid(x / 2.0, y / 2.0, z / 2.0),
+        Shape::Cylinder { radius, height } => Collider::cylinder(height / 2.0, radius),
+        Shape::Sphere { radius } => Collider::ball(radius),
+        // when no hull can be built, a box the same size still gives the player a floor
+        Shape::Ramp { size } => Collider::convex_hull(&ramp_corners(size)).unwrap_or_else(|| {
+            warn!("no collider for a ramp of size {size:?}, it gets a box instead");
+            let [x, y, z] = size;
+            Collider::cuboid(x / 2.0, y / 2.0, z / 2.0)
+        }),
+    }
+}
+
+// bottom four corners then the two top corners along the back edge
+fn ramp_corners([x, y, z]: [f32; 3]) -> [Vec3; 6] {
+    let (x, y, z) = (x / 2.0, y / 2.0, z / 2.0);
+    [
+        Vec3::new(-x, -y, -z),
+        Vec3::new(x, -y, -z),
+        Vec3::new(x, -y, z),
+        Vec3::new(-x, -y, z),
+        Vec3::new(-x, y, -z),
+        Vec3::new(x, y, -z),
+    ]
+}
+
+fn ramp_mesh(size: [f32; 3]) -> Mesh {
+    let c = ramp_corners(size);
+    // counter clockwise when looking at the face from outside
+    let triangles = [
+        [c[0], c[1], c[2]],
+        [c[0], c[2], c[3]],
+        [c[0], c[4], c[5]],
+        [c[0], c[5], c[1]],
+        [c[4], c[3], c[2]],
+        [c[4], c[2], c[5]],
+        [c[0], c[3], c[4]],
+        [c[1], c[5], c[2]],
+    ];
+
+    // flat shading, so every triangle gets its own vertices
+    let mut positions = Vec::new();
+    let mut normals = Vec::new();
+    for [a, b, c] in triangles {
+        let normal = (b - a).cross(c - a).normalize();
+        for vertex in [a, b, c] {
+            positions.push(vertex.to_array());
+            normals.push(normal.to_array());
+        }
+    }
+    let uvs = vec![[0.0, 0.0]; positions.len()];
 
-    commands.spawn(ceiling_light);
+    let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, normals);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, uvs);
+    mesh
 }
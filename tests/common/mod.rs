@@ -0,0 +1,147 @@
+// Shared by the integration tests: a headless game (see headless.rs) on a flat floor, with
+// time stepped by hand and inputs scripted frame by frame.
+#![allow(dead_code)]
+
+use std::time::Duration;
+
+use bevy::{ecs::event::ManualEventReader, prelude::*, time::TimeUpdateStrategy};
+use bevy_rapier3d::prelude::*;
+
+use bevy_fps_test::{
+    headless::HeadlessPlugin,
+    input::InputFrame,
+    jumbotile::{JumboTilePlugin, Kovaak},
+    map::TargetVolume,
+    player::{Player, PlayerCamera, PlayerPlugin, TargetHit, EYE_HEIGHT},
+    rng::GameRng,
+    world::{MapLoaded, SpawnPoints},
+};
+
+// fixed so target positions are the same every run
+const SEED: u64 = 1234;
+
+// The floor's top is at y = 0 and the player starts resting on it at the origin, looking
+// along +x. Nothing else is in the world until a test adds targets.
+pub struct TestGame {
+    pub app: App,
+    pub dt: f32,
+    // every TargetHit so far
+    pub hits: Vec<TargetHit>,
+    hit_reader: ManualEventReader<TargetHit>,
+}
+
+impl TestGame {
+    pub fn new(fps: f32) -> Self {
+        let mut app = App::new();
+        app.add_plugins((
+            MinimalPlugins,
+            HeadlessPlugin,
+            PlayerPlugin,
+            JumboTilePlugin,
+        ))
+        .insert_resource(GameRng::new(SEED))
+        .init_resource::<SpawnPoints>()
+        .add_event::<MapLoaded>()
+        .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_secs_f32(
+            1.0 / fps,
+        )));
+        app.world.spawn((
+            TransformBundle::from_transform(Transform::from_xyz(0.0, -0.5, 0.0)),
+            RigidBody::Fixed,
+            Collider::cuboid(500.0, 0.5, 500.0),
+        ));
+
+        let mut game = Self {
+            app,
+            dt: 1.0 / fps,
+            hits: Vec::new(),
+            hit_reader: ManualEventReader::default(),
+        };
+        // let the player settle and the weapon cooldowns run out
+        game.wait(1.0);
+        game
+    }
+
+    // one frame with these buttons held, they count as pressed too
+    pub fn step(&mut self, buttons: u16) {
+        *self.app.world.resource_mut::<InputFrame>() = InputFrame {
+            held: buttons,
+            pressed: buttons,
+            look: Vec2::ZERO,
+            dt: self.dt,
+        };
+        self.app.update();
+
+        let events = self.app.world.resource::<Events<TargetHit>>();
+        self.hits.extend(self.hit_reader.read(events).copied());
+    }
+
+    pub fn hold(&mut self, buttons: u16, seconds: f32) {
+        for _ in 0..self.frames(seconds) {
+            self.step(buttons);
+        }
+    }
+
+    pub fn wait(&mut self, seconds: f32) {
+        self.hold(0, seconds);
+    }
+
+    pub fn frames(&self, seconds: f32) -> usize {
+        (seconds / self.dt).round() as usize
+    }
+
+    pub fn position(&mut self) -> Vec3 {
+        self.player::<Transform>().translation
+    }
+
+    pub fn velocity(&mut self) -> Vec3 {
+        self.player::<Velocity>().linvel
+    }
+
+    pub fn eye(&mut self) -> Vec3 {
+        self.position() + Vec3::Y * EYE_HEIGHT
+    }
+
+    fn player<T: Component + Clone>(&mut self) -> T {
+        self.app
+            .world
+            .query_filtered::<&T, With<Player>>()
+            .single(&self.app.world)
+            .clone()
+    }
+
+    // turns the view, same angles as the mouse makes
+    pub fn look(&mut self, yaw: f32, pitch: f32) {
+        let mut cam = self
+            .app
+            .world
+            .query_filtered::<&mut Transform, With<PlayerCamera>>()
+            .single_mut(&mut self.app.world);
+        cam.rotation = Quat::from_axis_angle(Vec3::Y, yaw) * Quat::from_axis_angle(Vec3::X, pitch);
+    }
+
+    pub fn aim_at(&mut self, point: Vec3) {
+        let direction = (point - self.eye()).normalize();
+        self.look(
+            (-direction.x).atan2(-direction.z),
+            direction.y.clamp(-1.0, 1.0).asin(),
+        );
+    }
+
+    // puts targets in like a freshly loaded map would
+    pub fn spawn_targets(&mut self, volume: TargetVolume) {
+        self.app.world.resource_mut::<SpawnPoints>().targets = vec![volume];
+        self.app.world.send_event(MapLoaded);
+        // spawned, then picked up by the physics
+        self.wait(0.1);
+    }
+
+    pub fn targets(&mut self) -> Vec<Vec3> {
+        self.app
+            .world
+            .query_filtered::<&Transform, With<Kovaak>>()
+            .iter(&self.app.world)
+            .map(|transform| transform.translation)
+            .collect()
+    }
+}
@@ -0,0 +1,67 @@
+// Running and jumping on flat ground, checked against what Movement's defaults and the
+// physics settings work out to, at more than one frame rate.
+mod common;
+
+use bevy_fps_test::input::{FORWARD, JUMP, SPRINT};
+use common::TestGame;
+
+fn jump_apex(fps: f32) -> f32 {
+    let mut game = TestGame::new(fps);
+    let start = game.position().y;
+
+    game.step(JUMP);
+    let mut apex = start;
+    for _ in 0..game.frames(1.5) {
+        game.step(0);
+        apex = apex.max(game.position().y);
+    }
+    apex - start
+}
+
+#[test]
+fn jump_apex_height() {
+    // 5 m/s up against gravity 10 is 1.25 m, damping takes a little off
+    let apex = jump_apex(60.0);
+    assert!((1.0..=1.3).contains(&apex), "jumped {apex} m");
+}
+
+#[test]
+fn jump_apex_is_frame_rate_independent() {
+    let at_60 = jump_apex(60.0);
+    let at_144 = jump_apex(144.0);
+    assert!(
+        (at_60 - at_144).abs() < 0.1,
+        "jumped {at_60} m at 60 fps, {at_144} m at 144 fps"
+    );
+}
+
+// horizontal speed after holding these buttons for a second
+fn speed_after_one_second(fps: f32, buttons: u16) -> f32 {
+    let mut game = TestGame::new(fps);
+    game.hold(buttons, 1.0);
+    let velocity = game.velocity();
+    velocity.x.hypot(velocity.z)
+}
+
+#[test]
+fn sprint_speed() {
+    // Sprinting pushes 60 m/s² against 5 m/s² of ground friction. Nothing caps it but the
+    // damping, which only levels off far past where anyone sprints to, so the speed after a
+    // second is what's pinned down here: 55 / 0.2 * (1 - e^-0.2) = 49.8 m/s.
+    let sprint = speed_after_one_second(60.0, FORWARD | SPRINT);
+    assert!((45.0..=55.0).contains(&sprint), "sprinted to {sprint} m/s");
+
+    // walking is 10 m/s² against the same friction, 5 / 0.2 * (1 - e^-0.2) = 4.5 m/s
+    let walk = speed_after_one_second(60.0, FORWARD);
+    assert!((3.5..=5.5).contains(&walk), "walked to {walk} m/s");
+}
+
+#[test]
+fn sprint_speed_is_frame_rate_independent() {
+    let at_60 = speed_after_one_second(60.0, FORWARD | SPRINT);
+    let at_144 = speed_after_one_second(144.0, FORWARD | SPRINT);
+    assert!(
+        (at_60 - at_144).abs() < at_60 * 0.05,
+        "{at_60} m/s at 60 fps, {at_144} m/s at 144 fps"
+    );
+}
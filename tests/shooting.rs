@@ -0,0 +1,137 @@
+// The gun and the rocket launcher: shots landing on targets (through ShotTar), targets
+// moving once they go down, and rockets throwing the player up the same at any frame rate.
+mod common;
+
+use bevy::prelude::*;
+use bevy_fps_test::{
+    input::{ROCKET, SHOOT},
+    map::TargetVolume,
+};
+use common::TestGame;
+
+fn rocket_jump_height(fps: f32) -> f32 {
+    let mut game = TestGame::new(fps);
+    let start = game.position().y;
+
+    // straight down, a little forward so the ray isn't parallel to anything
+    game.look(0.0, -1.5);
+    game.step(ROCKET);
+    let mut apex = start;
+    for _ in 0..game.frames(3.0) {
+        game.step(0);
+        apex = apex.max(game.position().y);
+    }
+    apex - start
+}
+
+#[test]
+fn rocket_jump_launch_height() {
+    // Standing on the blast the player starts 0.5 m from its center and leaves it at 2 m,
+    // which throws them up at about 19 m/s. That's 18.75 m without damping, about 14.7 m
+    // with it.
+    let height = rocket_jump_height(60.0);
+    assert!((13.0..=16.5).contains(&height), "rocket jumped {height} m");
+}
+
+#[test]
+fn rocket_jump_is_frame_rate_independent() {
+    // blast_player hands out the whole push at once, a push stepped along frame by frame
+    // came out about 10% higher at 144 fps than at 60
+    let at_60 = rocket_jump_height(60.0);
+    let at_144 = rocket_jump_height(144.0);
+    assert!(
+        (at_60 - at_144).abs() < at_60 * 0.05,
+        "rocket jumped {at_60} m at 60 fps, {at_144} m at 144 fps"
+    );
+}
+
+// one target that always spawns at `at`
+fn single_target(at: Vec3, health: u32) -> TargetVolume {
+    TargetVolume {
+        min: at.into(),
+        max: at.into(),
+        count: 1,
+        health,
+    }
+}
+
+#[test]
+fn shot_hits_target() {
+    let mut game = TestGame::new(60.0);
+    let target = Vec3::new(10.0, 1.0, 0.0);
+    game.spawn_targets(single_target(target, 1));
+    assert_eq!(game.targets(), vec![target]);
+
+    game.aim_at(target);
+    game.step(SHOOT);
+    game.step(0);
+
+    assert_eq!(game.hits.len(), 1, "the shot missed");
+    let hit = game.hits[0];
+    // the near face of the cube
+    assert!((hit.point.x - 9.5).abs() < 0.01, "hit at {}", hit.point);
+    assert!(!hit.headshot);
+    assert!(hit.kill);
+}
+
+#[test]
+fn headshot_counts_double() {
+    let mut game = TestGame::new(60.0);
+    let target = Vec3::new(10.0, 1.0, 0.0);
+    game.spawn_targets(single_target(target, 2));
+
+    game.aim_at(target + Vec3::Y * 0.4);
+    game.step(SHOOT);
+    game.step(0);
+
+    assert_eq!(game.hits.len(), 1, "the shot missed");
+    assert!(game.hits[0].headshot);
+    // two hits of health in one
+    assert!(game.hits[0].kill);
+}
+
+#[test]
+fn wounded_target_stays_put() {
+    let mut game = TestGame::new(60.0);
+    let target = Vec3::new(10.0, 1.0, 0.0);
+    game.spawn_targets(single_target(target, 2));
+
+    game.aim_at(target);
+    game.step(SHOOT);
+    game.step(0);
+
+    assert_eq!(game.hits.len(), 1, "the shot missed");
+    assert!(!game.hits[0].kill);
+    assert_eq!(game.targets(), vec![target]);
+}
+
+#[test]
+fn killed_target_moves_inside_its_volume() {
+    let mut game = TestGame::new(60.0);
+    let (min, max) = (Vec3::new(20.0, 0.5, -5.0), Vec3::new(30.0, 4.0, 5.0));
+    game.spawn_targets(TargetVolume {
+        min: min.into(),
+        max: max.into(),
+        count: 1,
+        health: 1,
+    });
+
+    let mut last = game.targets()[0];
+    for shot in 0..10 {
+        game.aim_at(last);
+        game.step(SHOOT);
+        // past the gun's cooldown
+        game.wait(0.3);
+
+        assert_eq!(game.hits.len(), shot + 1, "shot {shot} missed");
+        assert!(game.hits[shot].kill);
+
+        let moved = game.targets()[0];
+        assert!(
+            moved.cmpge(min).all() && moved.cmple(max).all(),
+            "target moved to {moved}, outside {min}..{max}"
+        );
+        assert_ne!(moved, last, "target didn't move");
+        last = moved;
+    }
+}